@@ -0,0 +1,208 @@
+use crate::context::{AdminGuildId, State};
+use crate::framework::{AfterHook, Invocation};
+use crate::retry;
+use futures::future::BoxFuture;
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::future::IntoFuture;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+use thiserror::Error;
+use tokio::sync::{mpsc, oneshot};
+use tower::BoxError;
+use tracing::{Instrument, info_span, instrument, warn};
+use twilight_model::application::interaction::InteractionType;
+use twilight_model::channel::message::AllowedMentions;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, GuildMarker, InteractionMarker, UserMarker};
+
+#[derive(Debug, Error)]
+pub enum AuditError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Audit writer task failed: {0}")]
+    Join(#[from] tokio::task::JoinError),
+}
+
+/// A command used in the admin guild, as written to the audit log file.
+#[derive(Clone, Debug, Serialize)]
+pub struct AuditEntry {
+    /// Milliseconds since the unix epoch the command was used at.
+    pub timestamp_ms: u64,
+    pub interaction_id: Id<InteractionMarker>,
+    pub command: Option<String>,
+    pub user_id: Option<Id<UserMarker>>,
+    pub guild_id: Option<Id<GuildMarker>>,
+    pub elapsed_ms: u64,
+    pub error: Option<String>,
+}
+
+impl AuditEntry {
+    fn new(invocation: &Invocation, elapsed: Duration, result: Result<(), &BoxError>) -> Self {
+        let created_at = retry::interaction_created_at(invocation.interaction_id);
+        AuditEntry {
+            timestamp_ms: created_at
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis()
+                .try_into()
+                .unwrap_or(u64::MAX),
+            interaction_id: invocation.interaction_id,
+            command: invocation.name.clone(),
+            user_id: invocation.user_id,
+            guild_id: invocation.guild_id,
+            elapsed_ms: elapsed.as_millis().try_into().unwrap_or(u64::MAX),
+            error: result.err().map(ToString::to_string),
+        }
+    }
+
+    fn message(&self) -> String {
+        let command = self.command.as_deref().unwrap_or("unknown");
+        let user = self.user_id.map_or_else(
+            || "an unknown user".to_owned(),
+            |user_id| format!("<@{user_id}>"),
+        );
+        match &self.error {
+            None => format!("`/{command}` used by {user}, took {}ms.", self.elapsed_ms),
+            Some(error) => format!(
+                "`/{command}` used by {user} failed after {}ms: {error}",
+                self.elapsed_ms
+            ),
+        }
+    }
+}
+
+/// Message to the task writing the audit entries.
+enum AuditMessage {
+    Entry(AuditEntry),
+    /// Answered once the entries queued before it are written.
+    Flush(oneshot::Sender<()>),
+}
+
+/// Records commands used in the admin guild to a Discord channel and a JSON lines file.
+///
+/// The channel and file are read from the current configuration, so they can be changed by reloading it.
+/// Entries are written in the background, [`AuditSink::flush`] waits for them before shutting down.
+#[derive(Clone, Debug)]
+pub struct AuditSink {
+    state: Arc<State>,
+    sender: mpsc::UnboundedSender<AuditMessage>,
+}
+
+impl AuditSink {
+    /// Creates the sink, spawning the task writing its entries.
+    pub fn new(state: Arc<State>) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(
+            Self::write_entries(state.clone(), receiver).instrument(info_span!("audit writer")),
+        );
+        AuditSink { state, sender }
+    }
+
+    /// Waits until the entries recorded so far are written, returning whether that happened before the timeout.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn flush(&self, timeout: Duration) -> bool {
+        let (done, written) = oneshot::channel();
+        if self.sender.send(AuditMessage::Flush(done)).is_err() {
+            warn!("Audit writer stopped, entries may be lost");
+            return false;
+        }
+        let flushed = matches!(tokio::time::timeout(timeout, written).await, Ok(Ok(())));
+        if !flushed {
+            warn!("Audit entries still unwritten after flush timeout");
+        }
+        flushed
+    }
+
+    async fn write_entries(state: Arc<State>, mut receiver: mpsc::UnboundedReceiver<AuditMessage>) {
+        while let Some(message) = receiver.recv().await {
+            match message {
+                AuditMessage::Entry(entry) => Self::write(&state, &entry).await,
+                // Nobody waiting for the flush anymore is fine
+                AuditMessage::Flush(done) => _ = done.send(()),
+            }
+        }
+    }
+
+    async fn write(state: &State, entry: &AuditEntry) {
+        let config = state.config();
+        if let Some(file) = &config.audit_file
+            && let Err(error) = Self::append(file.clone(), entry).await
+        {
+            warn!(%error, "Could not write audit entry to file");
+        }
+        if let Some(channel_id) = config.audit_channel_id
+            && let Err(error) = Self::send(state, channel_id, entry).await
+        {
+            warn!(%error, "Could not send audit entry to channel");
+        }
+    }
+
+    fn is_audited(&self, invocation: &Invocation) -> bool {
+        let admin_guild_id = self.state.extensions.get::<AdminGuildId>();
+        invocation.kind == InteractionType::ApplicationCommand
+            && admin_guild_id.is_some_and(|AdminGuildId(admin_guild_id)| {
+                invocation.guild_id == Some(*admin_guild_id)
+            })
+    }
+
+    async fn append(file: PathBuf, entry: &AuditEntry) -> Result<(), AuditError> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        tokio::task::spawn_blocking(move || {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(file)?
+                .write_all(&line)
+        })
+        .await??;
+        Ok(())
+    }
+
+    async fn send(
+        state: &State,
+        channel_id: Id<ChannelMarker>,
+        entry: &AuditEntry,
+    ) -> Result<(), twilight_http::Error> {
+        let content = entry.message();
+        let allowed_mentions = AllowedMentions::default();
+        state
+            .retry_policy
+            .retry(retry::token_deadline(entry.interaction_id), || {
+                state
+                    .client
+                    .create_message(channel_id)
+                    .content(&content)
+                    .allowed_mentions(Some(&allowed_mentions))
+                    .into_future()
+            })
+            .await?;
+        Ok(())
+    }
+}
+
+impl AfterHook for AuditSink {
+    #[instrument(level = "debug", skip(self, result))]
+    fn after<'a>(
+        &'a self,
+        invocation: &'a Invocation,
+        elapsed: Duration,
+        result: Result<(), &'a BoxError>,
+    ) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            if !self.is_audited(invocation) {
+                return;
+            }
+
+            let entry = AuditEntry::new(invocation, elapsed, result);
+            if self.sender.send(AuditMessage::Entry(entry)).is_err() {
+                warn!("Audit writer stopped, dropping entry");
+            }
+        })
+    }
+}
@@ -0,0 +1,151 @@
+use crate::storage::{Storage, StorageError};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::sync::{PoisonError, RwLock};
+use tracing::{info, instrument, warn};
+use twilight_model::id::Id;
+use twilight_model::id::marker::{GuildMarker, UserMarker};
+
+/// A user or guild that is not allowed to use the bot.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum BlockTarget {
+    User(Id<UserMarker>),
+    Guild(Id<GuildMarker>),
+}
+
+impl BlockTarget {
+    fn from_row(kind: &str, id: i64) -> Option<Self> {
+        let id = id.cast_unsigned();
+        match kind {
+            "user" => Id::new_checked(id).map(BlockTarget::User),
+            "guild" => Id::new_checked(id).map(BlockTarget::Guild),
+            _ => None,
+        }
+    }
+
+    fn kind(self) -> &'static str {
+        match self {
+            BlockTarget::User(_) => "user",
+            BlockTarget::Guild(_) => "guild",
+        }
+    }
+
+    fn id(self) -> i64 {
+        match self {
+            BlockTarget::User(user_id) => user_id.get().cast_signed(),
+            BlockTarget::Guild(guild_id) => guild_id.get().cast_signed(),
+        }
+    }
+}
+
+impl Display for BlockTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlockTarget::User(user_id) => write!(f, "user `{user_id}`"),
+            BlockTarget::Guild(guild_id) => write!(f, "server `{guild_id}`"),
+        }
+    }
+}
+
+/// Users and guilds whose interactions are rejected, persisted in storage and kept in memory.
+#[derive(Debug)]
+pub struct Blocklist {
+    storage: Storage,
+    /// Blocked targets with the reason they were blocked for.
+    entries: RwLock<BTreeMap<BlockTarget, Option<String>>>,
+}
+
+impl Blocklist {
+    /// Loads the blocked targets from storage.
+    #[instrument(level = "info", skip(storage))]
+    pub async fn load(storage: Storage) -> Result<Self, StorageError> {
+        let rows = storage
+            .with_connection(|connection| {
+                let mut statement =
+                    connection.prepare("SELECT kind, target_id, reason FROM blocklist")?;
+                let rows = statement
+                    .query_map([], |row| {
+                        Ok((row.get::<_, String>(0)?, row.get(1)?, row.get(2)?))
+                    })?
+                    .collect::<Result<Vec<(String, i64, Option<String>)>, _>>()?;
+                Ok(rows)
+            })
+            .await?;
+
+        let mut entries = BTreeMap::new();
+        for (kind, id, reason) in rows {
+            if let Some(target) = BlockTarget::from_row(&kind, id) {
+                entries.insert(target, reason);
+            } else {
+                warn!(kind, id, "Ignoring invalid blocklist entry");
+            }
+        }
+        info!(entries = entries.len(), "Loaded blocklist");
+        Ok(Blocklist {
+            storage,
+            entries: RwLock::new(entries),
+        })
+    }
+
+    pub fn is_blocked(&self, target: BlockTarget) -> bool {
+        self.entries
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains_key(&target)
+    }
+
+    /// All blocked targets with the reason they were blocked for.
+    pub fn entries(&self) -> Vec<(BlockTarget, Option<String>)> {
+        self.entries
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|(target, reason)| (*target, reason.clone()))
+            .collect()
+    }
+
+    /// Blocks the target, replacing the reason if it was blocked already.
+    #[instrument(level = "info", skip(self))]
+    pub async fn block(
+        &self,
+        target: BlockTarget,
+        reason: Option<String>,
+    ) -> Result<(), StorageError> {
+        let stored_reason = reason.clone();
+        self.storage
+            .with_connection(move |connection| {
+                connection.execute(
+                    "INSERT INTO blocklist (kind, target_id, reason) VALUES (?1, ?2, ?3)
+                    ON CONFLICT (kind, target_id) DO UPDATE SET reason = excluded.reason",
+                    (target.kind(), target.id(), stored_reason),
+                )?;
+                Ok(())
+            })
+            .await?;
+        self.entries
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(target, reason);
+        Ok(())
+    }
+
+    /// Unblocks the target, returning whether it was blocked.
+    #[instrument(level = "info", skip(self))]
+    pub async fn unblock(&self, target: BlockTarget) -> Result<bool, StorageError> {
+        self.storage
+            .with_connection(move |connection| {
+                connection.execute(
+                    "DELETE FROM blocklist WHERE kind = ?1 AND target_id = ?2",
+                    (target.kind(), target.id()),
+                )?;
+                Ok(())
+            })
+            .await?;
+        let removed = self
+            .entries
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&target);
+        Ok(removed.is_some())
+    }
+}
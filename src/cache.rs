@@ -0,0 +1,128 @@
+use serde::Deserialize;
+use std::ops::Deref;
+use tracing::instrument;
+use twilight_cache_inmemory::{DefaultInMemoryCache, ResourceType};
+use twilight_gateway::{Event, EventTypeFlags, Intents};
+
+/// Resources that can be cached, configured as a comma separated list.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CachedResource {
+    Guilds,
+    Channels,
+    Members,
+    Roles,
+}
+
+impl CachedResource {
+    fn resource_type(self) -> ResourceType {
+        match self {
+            CachedResource::Guilds => ResourceType::GUILD,
+            CachedResource::Channels => ResourceType::CHANNEL,
+            CachedResource::Members => ResourceType::MEMBER,
+            CachedResource::Roles => ResourceType::ROLE,
+        }
+    }
+
+    fn intents(self) -> Intents {
+        match self {
+            CachedResource::Guilds | CachedResource::Channels | CachedResource::Roles => {
+                Intents::GUILDS
+            }
+            // Privileged, has to be enabled for the application as well
+            CachedResource::Members => Intents::GUILDS | Intents::GUILD_MEMBERS,
+        }
+    }
+
+    fn event_types(self) -> EventTypeFlags {
+        // Guilds arrive with their channels, members and roles
+        let guild = EventTypeFlags::GUILD_CREATE | EventTypeFlags::GUILD_DELETE;
+        guild
+            | match self {
+                CachedResource::Guilds => {
+                    EventTypeFlags::GUILD_UPDATE | EventTypeFlags::UNAVAILABLE_GUILD
+                }
+                CachedResource::Channels => {
+                    EventTypeFlags::CHANNEL_CREATE
+                        | EventTypeFlags::CHANNEL_UPDATE
+                        | EventTypeFlags::CHANNEL_DELETE
+                        | EventTypeFlags::THREAD_CREATE
+                        | EventTypeFlags::THREAD_UPDATE
+                        | EventTypeFlags::THREAD_DELETE
+                        | EventTypeFlags::THREAD_LIST_SYNC
+                }
+                CachedResource::Members => {
+                    EventTypeFlags::MEMBER_ADD
+                        | EventTypeFlags::MEMBER_UPDATE
+                        | EventTypeFlags::MEMBER_REMOVE
+                        | EventTypeFlags::MEMBER_CHUNK
+                }
+                CachedResource::Roles => {
+                    EventTypeFlags::ROLE_CREATE
+                        | EventTypeFlags::ROLE_UPDATE
+                        | EventTypeFlags::ROLE_DELETE
+                }
+            }
+    }
+}
+
+/// In-memory cache of the configured resources, updated by the shard runners.
+#[derive(Debug)]
+pub struct Cache {
+    inner: DefaultInMemoryCache,
+    intents: Intents,
+    event_types: EventTypeFlags,
+}
+
+impl Cache {
+    /// Creates a cache for the resources, or returns `None` if no resources should be cached.
+    pub fn new(resources: &[CachedResource]) -> Option<Self> {
+        if resources.is_empty() {
+            return None;
+        }
+
+        let resource_types = resources
+            .iter()
+            .fold(ResourceType::empty(), |types, resource| {
+                types | resource.resource_type()
+            });
+        Some(Cache {
+            inner: DefaultInMemoryCache::builder()
+                .resource_types(resource_types)
+                .build(),
+            intents: resources
+                .iter()
+                .fold(Intents::empty(), |intents, resource| {
+                    intents | resource.intents()
+                }),
+            event_types: resources
+                .iter()
+                .fold(EventTypeFlags::empty(), |event_types, resource| {
+                    event_types | resource.event_types()
+                }),
+        })
+    }
+
+    /// Intents needed to receive the events the cache is built from.
+    pub fn intents(&self) -> Intents {
+        self.intents
+    }
+
+    /// Events the cache is built from.
+    pub fn event_types(&self) -> EventTypeFlags {
+        self.event_types
+    }
+
+    #[instrument(level = "trace", skip_all)]
+    pub fn update(&self, event: &Event) {
+        self.inner.update(event);
+    }
+}
+
+impl Deref for Cache {
+    type Target = DefaultInMemoryCache;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
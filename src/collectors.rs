@@ -0,0 +1,249 @@
+use crate::util::OmitDebug;
+use std::fmt::{Debug, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{instrument, trace};
+use twilight_model::application::interaction::{Interaction, InteractionData, InteractionType};
+use twilight_model::id::Id;
+use twilight_model::id::marker::{MessageMarker, UserMarker};
+
+/// Component interactions a collector waits for, everything not restricted matches.
+#[derive(Clone, Eq, PartialEq, Default, Debug)]
+pub struct ComponentFilter {
+    message_id: Option<Id<MessageMarker>>,
+    user_id: Option<Id<UserMarker>>,
+    custom_ids: Vec<String>,
+}
+
+impl ComponentFilter {
+    pub fn new() -> Self {
+        ComponentFilter::default()
+    }
+
+    /// Only components on this message.
+    pub fn message(mut self, message_id: Id<MessageMarker>) -> Self {
+        self.message_id = Some(message_id);
+        self
+    }
+
+    /// Only components used by this user.
+    pub fn user(mut self, user_id: Id<UserMarker>) -> Self {
+        self.user_id = Some(user_id);
+        self
+    }
+
+    /// Only components with one of the added custom ids.
+    pub fn custom_id(mut self, custom_id: impl Into<String>) -> Self {
+        self.custom_ids.push(custom_id.into());
+        self
+    }
+
+    fn matches(&self, interaction: &Interaction) -> bool {
+        let Some(InteractionData::MessageComponent(data)) = &interaction.data else {
+            return false;
+        };
+        let message_id = interaction.message.as_ref().map(|message| message.id);
+
+        self.message_id.is_none_or(|id| message_id == Some(id))
+            && self
+                .user_id
+                .is_none_or(|id| interaction.author_id() == Some(id))
+            && (self.custom_ids.is_empty() || self.custom_ids.contains(&data.custom_id))
+    }
+}
+
+struct Waiting {
+    id: u64,
+    filter: ComponentFilter,
+    sender: mpsc::UnboundedSender<Interaction>,
+}
+
+/// Component interactions handlers are waiting for, delivered before normal routing.
+#[derive(Default)]
+pub struct Collectors {
+    next_id: AtomicU64,
+    waiting: Mutex<Vec<Waiting>>,
+}
+
+impl Debug for Collectors {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Collectors")
+            .field("next_id", &self.next_id)
+            .field("waiting", &OmitDebug)
+            .finish()
+    }
+}
+
+/// Receives the component interactions matching its filter until dropped.
+///
+/// Interactions arriving while nobody waits on the collector are kept for the next wait.
+pub struct Collector<'a> {
+    collectors: &'a Collectors,
+    id: u64,
+    receiver: mpsc::UnboundedReceiver<Interaction>,
+}
+
+impl Debug for Collector<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Collector")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Collector<'_> {
+    /// Waits for the next matching component interaction, or `None` after the timeout.
+    #[instrument(level = "debug", skip(self), fields(collector = self.id))]
+    pub async fn next(&mut self, timeout: Duration) -> Option<Interaction> {
+        tokio::time::timeout(timeout, self.receiver.recv())
+            .await
+            .ok()
+            .flatten()
+    }
+}
+
+impl Drop for Collector<'_> {
+    fn drop(&mut self) {
+        let mut waiting = self
+            .collectors
+            .waiting
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        waiting.retain(|waiting| waiting.id != self.id);
+    }
+}
+
+impl Collectors {
+    /// Starts collecting component interactions matching the filter.
+    ///
+    /// Register the collector before sending the components, so fast presses are not missed.
+    pub fn collect(&self, filter: ComponentFilter) -> Collector<'_> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.waiting
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Waiting { id, filter, sender });
+        Collector {
+            collectors: self,
+            id,
+            receiver,
+        }
+    }
+
+    /// Hands the interaction to the oldest matching collector, or returns it if none wants it.
+    #[instrument(level = "trace", skip_all, fields(interaction.id = %interaction.id))]
+    pub fn deliver(&self, mut interaction: Interaction) -> Option<Interaction> {
+        if interaction.kind != InteractionType::MessageComponent {
+            return Some(interaction);
+        }
+
+        let mut waiting = self.waiting.lock().unwrap_or_else(PoisonError::into_inner);
+        while let Some(index) = waiting
+            .iter()
+            .position(|waiting| waiting.filter.matches(&interaction))
+        {
+            match waiting[index].sender.send(interaction) {
+                Ok(()) => {
+                    trace!(collector = waiting[index].id, "Delivered to collector");
+                    return None;
+                }
+                // The collector was dropped but was not removed yet
+                Err(returned) => {
+                    waiting.remove(index);
+                    interaction = returned.0;
+                }
+            }
+        }
+        Some(interaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Button press on a message, the interaction type can be changed to check it is ignored.
+    fn press(kind: u8, message_id: u64, user_id: u64, custom_id: &str) -> Interaction {
+        let user = |id: u64| json!({"id": id.to_string(), "username": "user", "discriminator": "0", "avatar": null});
+        serde_json::from_value(json!({
+            "id": "10",
+            "application_id": "1",
+            "type": kind,
+            "token": "token",
+            "authorizing_integration_owners": {},
+            "entitlements": [],
+            "user": user(user_id),
+            "message": {
+                "id": message_id.to_string(),
+                "channel_id": "5",
+                "author": user(1),
+                "content": "",
+                "timestamp": "2026-01-01T00:00:00+00:00",
+                "edited_timestamp": null,
+                "tts": false,
+                "mention_everyone": false,
+                "mentions": [],
+                "mention_roles": [],
+                "attachments": [],
+                "embeds": [],
+                "pinned": false,
+                "type": 0
+            },
+            "data": {"custom_id": custom_id, "component_type": 2, "components": []},
+            "version": 1
+        }))
+        .unwrap()
+    }
+
+    fn button(message_id: u64, user_id: u64, custom_id: &str) -> Interaction {
+        press(3, message_id, user_id, custom_id)
+    }
+
+    #[test]
+    fn matches() {
+        let filter = ComponentFilter::new()
+            .message(Id::new(4))
+            .user(Id::new(3))
+            .custom_id("yes")
+            .custom_id("no");
+        let cases = vec![
+            ("matching", button(4, 3, "yes"), true),
+            ("other custom id of the filter", button(4, 3, "no"), true),
+            ("different message", button(5, 3, "yes"), false),
+            ("different user", button(4, 6, "yes"), false),
+            ("different custom id", button(4, 3, "maybe"), false),
+            ("modal submit", press(5, 4, 3, "yes"), false),
+        ];
+        for (case, interaction, expected) in cases {
+            assert_eq!(filter.matches(&interaction), expected, "{case}");
+        }
+    }
+
+    #[test]
+    fn empty_filter_matches_any_component() {
+        let filter = ComponentFilter::new();
+        assert!(filter.matches(&button(4, 3, "yes")));
+        assert!(filter.matches(&button(5, 6, "maybe")));
+    }
+
+    #[test]
+    fn deliver_to_oldest_matching_collector() {
+        let collectors = Collectors::default();
+        let mut other = collectors.collect(ComponentFilter::new().custom_id("other"));
+        let mut first = collectors.collect(ComponentFilter::new());
+        let mut second = collectors.collect(ComponentFilter::new());
+
+        assert!(collectors.deliver(button(4, 3, "yes")).is_none());
+        assert!(first.receiver.try_recv().is_ok());
+        assert!(second.receiver.try_recv().is_err());
+        assert!(other.receiver.try_recv().is_err());
+
+        drop(first);
+        drop(second);
+        assert!(collectors.deliver(button(4, 3, "yes")).is_some());
+    }
+}
@@ -0,0 +1,71 @@
+use crate::commands::TwilightError;
+use crate::context::CommandContext;
+use crate::framework::CommandHandler;
+use crate::jobs::announcement::{self, Announcement};
+use crate::scheduler::SchedulerError;
+use chrono::{TimeDelta, Utc};
+use thiserror::Error;
+use tracing::instrument;
+use twilight_interactions::command::{CommandModel, CreateCommand};
+use twilight_model::application::interaction::InteractionChannel;
+use twilight_model::channel::message::MessageFlags;
+use twilight_util::builder::InteractionResponseDataBuilder;
+
+#[derive(Debug, CreateCommand, CommandModel)]
+#[command(name = "announce", desc = "Send a message to a channel later.")]
+pub struct Command {
+    /// Channel to send the message to
+    #[command(channel_types = "guild_text guild_announcement")]
+    channel: InteractionChannel,
+    /// Message to send
+    #[command(max_length = 2000)]
+    message: String,
+    /// Minutes until the message is sent
+    #[command(min_value = 0, max_value = 525_600)]
+    in_minutes: i64,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Error replying to interaction: {0}")]
+    Reply(#[from] TwilightError),
+    #[error("Error scheduling the announcement: {0}")]
+    Schedule(#[from] SchedulerError),
+}
+
+impl CommandHandler for Command {
+    type Context = CommandContext;
+    type Response = ();
+    type Error = Error;
+
+    const ADMIN_ONLY: bool = true;
+
+    #[instrument(level = "info")]
+    async fn handle(self, context: Self::Context) -> Result<Self::Response, Self::Error> {
+        let due_at = Utc::now() + TimeDelta::minutes(self.in_minutes);
+        let announcement = Announcement {
+            channel_id: self.channel.id,
+            content: self.message,
+        };
+        context
+            .state
+            .scheduler()
+            .schedule(announcement::KIND, &announcement, due_at)
+            .await?;
+
+        context
+            .reply(
+                InteractionResponseDataBuilder::new()
+                    .content(format!(
+                        "The message will be sent to <#{}> <t:{}:R>.",
+                        announcement.channel_id,
+                        due_at.timestamp()
+                    ))
+                    .flags(MessageFlags::EPHEMERAL)
+                    .build(),
+            )
+            .await
+            .map_err(TwilightError::from)?;
+        Ok(())
+    }
+}
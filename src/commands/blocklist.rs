@@ -0,0 +1,141 @@
+use crate::blocklist::BlockTarget;
+use crate::commands::TwilightError;
+use crate::context::CommandContext;
+use crate::framework::CommandHandler;
+use crate::storage::StorageError;
+use crate::util::{join_lines, truncate};
+use thiserror::Error;
+use tracing::instrument;
+use twilight_interactions::command::{CommandModel, CommandOption, CreateCommand, CreateOption};
+use twilight_model::channel::message::MessageFlags;
+use twilight_util::builder::InteractionResponseDataBuilder;
+
+/// Longest part of a block reason shown by `/blocklist list`.
+const MAX_LISTED_REASON: usize = 150;
+
+#[derive(Debug, CreateCommand, CommandModel)]
+#[command(
+    name = "blocklist",
+    desc = "Manage the users and servers that can't use the bot."
+)]
+pub enum Command {
+    #[command(name = "add")]
+    Add(Add),
+    #[command(name = "remove")]
+    Remove(Remove),
+    #[command(name = "list")]
+    List(List),
+}
+
+#[derive(Copy, Clone, Debug, CommandOption, CreateOption)]
+pub enum TargetKind {
+    #[option(name = "User", value = "user")]
+    User,
+    #[option(name = "Server", value = "guild")]
+    Guild,
+}
+
+#[derive(Debug, CreateCommand, CommandModel)]
+#[command(name = "add", desc = "Block a user or server.")]
+pub struct Add {
+    /// Whether to block a user or a server
+    kind: TargetKind,
+    /// Id of the user or server
+    id: String,
+    /// Why they are blocked
+    reason: Option<String>,
+}
+
+#[derive(Debug, CreateCommand, CommandModel)]
+#[command(name = "remove", desc = "Unblock a user or server.")]
+pub struct Remove {
+    /// Whether to unblock a user or a server
+    kind: TargetKind,
+    /// Id of the user or server
+    id: String,
+}
+
+#[derive(Debug, CreateCommand, CommandModel)]
+#[command(name = "list", desc = "Show the blocked users and servers.")]
+pub struct List;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Error replying to interaction: {0}")]
+    Reply(#[from] TwilightError),
+    #[error("Error changing the blocklist: {0}")]
+    Storage(#[from] StorageError),
+}
+
+fn target(kind: TargetKind, id: &str) -> Option<BlockTarget> {
+    let id = id.trim();
+    match kind {
+        TargetKind::User => id.parse().ok().map(BlockTarget::User),
+        TargetKind::Guild => id.parse().ok().map(BlockTarget::Guild),
+    }
+}
+
+impl Command {
+    /// Changes or lists the blocklist, returning the reply.
+    async fn run(self, context: &CommandContext) -> Result<String, Error> {
+        let blocklist = &context.state.blocklist;
+        match self {
+            Command::Add(Add { kind, id, reason }) => {
+                let Some(target) = target(kind, &id) else {
+                    return Ok(format!("`{id}` is not an id."));
+                };
+                blocklist.block(target, reason).await?;
+                Ok(format!("Blocked {target}."))
+            }
+            Command::Remove(Remove { kind, id }) => {
+                let Some(target) = target(kind, &id) else {
+                    return Ok(format!("`{id}` is not an id."));
+                };
+                if blocklist.unblock(target).await? {
+                    Ok(format!("Unblocked {target}."))
+                } else {
+                    Ok(format!("Nothing changed, {target} was not blocked."))
+                }
+            }
+            Command::List(List) => {
+                let entries = blocklist.entries();
+                if entries.is_empty() {
+                    return Ok("Nobody is blocked.".to_owned());
+                }
+                let lines: Vec<_> = entries
+                    .into_iter()
+                    .map(|(target, reason)| match reason {
+                        Some(reason) => {
+                            format!("- {target}: {}", truncate(&reason, MAX_LISTED_REASON))
+                        }
+                        None => format!("- {target}"),
+                    })
+                    .collect();
+                Ok(join_lines(&lines))
+            }
+        }
+    }
+}
+
+impl CommandHandler for Command {
+    type Context = CommandContext;
+    type Response = ();
+    type Error = Error;
+
+    const ADMIN_ONLY: bool = true;
+
+    #[instrument(level = "info")]
+    async fn handle(self, context: Self::Context) -> Result<Self::Response, Self::Error> {
+        let content = self.run(&context).await?;
+        context
+            .reply(
+                InteractionResponseDataBuilder::new()
+                    .content(content)
+                    .flags(MessageFlags::EPHEMERAL)
+                    .build(),
+            )
+            .await
+            .map_err(TwilightError::from)?;
+        Ok(())
+    }
+}
@@ -1,17 +1,30 @@
+// The autocomplete CommandModel derive generates code triggering this lint
+#![allow(clippy::needless_continue)]
+
 use super::CommandHandler;
 use crate::commands::TwilightError;
 use crate::context::CommandContext;
 use tracing::instrument;
-use twilight_interactions::command::{CommandModel, CreateCommand};
+use twilight_interactions::command::{AutocompleteValue, CommandModel, CreateCommand};
+use twilight_model::application::command::{CommandOptionChoice, CommandOptionChoiceValue};
 use twilight_util::builder::InteractionResponseDataBuilder;
 
 #[derive(Debug, CreateCommand, CommandModel)]
 #[command(name = "test-command-2", desc = "Just a test command tbh tbh.")]
 pub struct Command {
     /// The message to send
+    #[command(autocomplete = true)]
     message: String,
 }
 
+#[derive(Debug, CommandModel)]
+#[command(autocomplete = true)]
+pub struct Autocomplete {
+    message: AutocompleteValue<String>,
+}
+
+const SUGGESTIONS: [&str; 3] = ["Hello!", "Good morning!", "Good night!"];
+
 pub type Error = TwilightError;
 
 impl CommandHandler for Command {
@@ -31,3 +44,35 @@ impl CommandHandler for Command {
         Ok(())
     }
 }
+
+impl CommandHandler for Autocomplete {
+    type Context = CommandContext;
+    type Response = ();
+    type Error = Error;
+
+    #[instrument(level = "debug")]
+    async fn handle(self, context: Self::Context) -> Result<Self::Response, Self::Error> {
+        let AutocompleteValue::Focused(partial) = self.message else {
+            return Ok(());
+        };
+        let typed = (!partial.is_empty()).then(|| partial.clone());
+        let suggestions = SUGGESTIONS
+            .into_iter()
+            .filter(|suggestion| {
+                suggestion
+                    .to_lowercase()
+                    .starts_with(&partial.to_lowercase())
+            })
+            .map(str::to_owned);
+        let choices = typed
+            .into_iter()
+            .chain(suggestions)
+            .map(|message| CommandOptionChoice {
+                name: message.clone(),
+                name_localizations: None,
+                value: CommandOptionChoiceValue::String(message),
+            });
+        context.autocomplete(choices).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+use super::CommandHandler;
+use crate::collectors::ComponentFilter;
+use crate::commands::TwilightError;
+use crate::components::button;
+use crate::context::CommandContext;
+use crate::retry;
+use crate::storage::{RecordOwner, Records, StorageError};
+use std::fmt::Write;
+use std::future::IntoFuture;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::instrument;
+use twilight_interactions::command::{CommandModel, CreateCommand};
+use twilight_model::channel::message::Component;
+use twilight_model::channel::message::component::{ActionRow, ButtonStyle};
+use twilight_model::http::interaction::{InteractionResponseData, InteractionResponseType};
+use twilight_model::id::Id;
+use twilight_model::id::marker::InteractionMarker;
+use twilight_util::builder::InteractionResponseDataBuilder;
+
+/// Time the counter waits for the next button press.
+const PRESS_TIMEOUT: Duration = Duration::from_mins(1);
+/// Custom id prefixes of the buttons, followed by the id of the command interaction.
+const INCREMENT: &str = "counter:increment";
+const DONE: &str = "counter:done";
+/// Record key of the highest count, stored per user and per guild.
+const BEST_KEY: &str = "counter_best";
+
+#[derive(Debug, CreateCommand, CommandModel)]
+#[command(name = "counter", desc = "Count how often you press a button.")]
+pub struct Command;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Error replying to interaction: {0}")]
+    Reply(#[from] TwilightError),
+    #[error("Error storing the best count: {0}")]
+    Storage(#[from] StorageError),
+}
+
+/// Custom ids of the increment and done buttons of the counter started by the interaction.
+fn custom_ids(interaction_id: Id<InteractionMarker>) -> (String, String) {
+    (
+        format!("{INCREMENT}:{interaction_id}"),
+        format!("{DONE}:{interaction_id}"),
+    )
+}
+
+fn counter_message(count: u32, (increment, done): &(String, String)) -> InteractionResponseData {
+    InteractionResponseDataBuilder::new()
+        .content(format!("Count: {count}"))
+        .components([Component::ActionRow(ActionRow {
+            components: vec![
+                button(increment, "+1", ButtonStyle::Primary),
+                button(done, "Done", ButtonStyle::Secondary),
+            ],
+        })])
+        .build()
+}
+
+/// Stores the count if it beats the best count, returning the best count.
+async fn record_best<TOwner: RecordOwner>(
+    records: &Records<TOwner>,
+    owner_id: Id<TOwner>,
+    count: u32,
+) -> Result<u64, StorageError> {
+    records.set_max(owner_id, BEST_KEY, count.into()).await
+}
+
+/// Records the count for the user and the guild, describing the result.
+async fn finish(context: &CommandContext, count: u32) -> Result<String, StorageError> {
+    let storage = context.state.storage();
+    let mut summary = format!("Counted to {count}.");
+    if let Some(user_id) = context.interaction.author_id() {
+        let best = record_best(&storage.users(), user_id, count).await?;
+        _ = write!(summary, " Your best is {best}.");
+    }
+    if let Some(guild_id) = context.interaction.guild_id {
+        let best = record_best(&storage.guilds(), guild_id, count).await?;
+        _ = write!(summary, " The server's best is {best}.");
+    }
+    Ok(summary)
+}
+
+impl CommandHandler for Command {
+    type Context = CommandContext;
+    type Response = ();
+    type Error = Error;
+
+    #[instrument(level = "info")]
+    async fn handle(self, context: Self::Context) -> Result<Self::Response, Self::Error> {
+        let custom_ids = custom_ids(context.interaction.id);
+        let mut filter = ComponentFilter::new()
+            .custom_id(&custom_ids.0)
+            .custom_id(&custom_ids.1);
+        if let Some(user_id) = context.interaction.author_id() {
+            filter = filter.user(user_id);
+        }
+        // Collecting before the buttons are sent, the message id is not known yet
+        let mut presses = context.collect(filter);
+        context
+            .reply(counter_message(0, &custom_ids))
+            .await
+            .map_err(TwilightError::from)?;
+
+        let mut count = 0;
+        while let Some(press) = presses.next(PRESS_TIMEOUT).await {
+            if press.custom_id() == Some(custom_ids.1.as_str()) {
+                let summary = finish(&context, count).await?;
+                press
+                    .respond(
+                        InteractionResponseType::UpdateMessage,
+                        Some(
+                            InteractionResponseDataBuilder::new()
+                                .content(summary)
+                                .components([])
+                                .build(),
+                        ),
+                    )
+                    .await
+                    .map_err(TwilightError::from)?;
+                return Ok(());
+            }
+
+            count += 1;
+            press
+                .respond(
+                    InteractionResponseType::UpdateMessage,
+                    Some(counter_message(count, &custom_ids)),
+                )
+                .await
+                .map_err(TwilightError::from)?;
+        }
+
+        // Timed out, remove the buttons nobody is listening to anymore
+        let content = finish(&context, count).await?;
+        let client = context.state.interaction_client();
+        context
+            .state
+            .retry_policy
+            .retry(retry::token_deadline(context.interaction.id), || {
+                client
+                    .update_response(&context.interaction.token)
+                    .content(Some(&content))
+                    .components(Some(&[]))
+                    .into_future()
+            })
+            .await
+            .map_err(TwilightError::from)?;
+        Ok(())
+    }
+}
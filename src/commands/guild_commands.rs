@@ -0,0 +1,112 @@
+use crate::commands::{TwilightError, is_configurable_command};
+use crate::context::CommandContext;
+use crate::framework::CommandHandler;
+use crate::storage::StorageError;
+use std::convert::Infallible;
+use thiserror::Error;
+use tracing::{info, instrument};
+use twilight_interactions::command::{CommandModel, CreateCommand};
+use twilight_model::channel::message::MessageFlags;
+use twilight_model::id::Id;
+use twilight_model::id::marker::GuildMarker;
+use twilight_util::builder::InteractionResponseDataBuilder;
+
+#[derive(Debug, CreateCommand, CommandModel)]
+#[command(
+    name = "guild-commands",
+    desc = "Disable or enable commands in a server."
+)]
+pub enum Command {
+    #[command(name = "disable")]
+    Disable(Disable),
+    #[command(name = "enable")]
+    Enable(Enable),
+}
+
+#[derive(Debug, CreateCommand, CommandModel)]
+#[command(name = "disable", desc = "Disable a command in a server.")]
+pub struct Disable {
+    /// Id of the server
+    guild: String,
+    /// Name of the command
+    command: String,
+}
+
+#[derive(Debug, CreateCommand, CommandModel)]
+#[command(name = "enable", desc = "Enable a disabled command in a server again.")]
+pub struct Enable {
+    /// Id of the server
+    guild: String,
+    /// Name of the command
+    command: String,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Error replying to interaction: {0}")]
+    Reply(#[from] TwilightError),
+    #[error("Error accessing the settings: {0}")]
+    Storage(#[from] StorageError),
+}
+
+impl Command {
+    /// Changes the guild's disabled commands, returning the reply.
+    async fn run(self, context: &CommandContext) -> Result<String, Error> {
+        let (guild, command, disable) = match self {
+            Command::Disable(Disable { guild, command }) => (guild, command, true),
+            Command::Enable(Enable { guild, command }) => (guild, command, false),
+        };
+        let Ok(guild_id) = guild.trim().parse::<Id<GuildMarker>>() else {
+            return Ok(format!("`{guild}` is not a server id."));
+        };
+        let command = command.trim().trim_start_matches('/');
+        if !is_configurable_command(command) {
+            return Ok(format!("There is no command called `{command}`."));
+        }
+
+        let disabled = command.to_owned();
+        let settings = context.state.guild_settings();
+        let Ok(changed) = settings
+            .update(guild_id, move |settings| {
+                let changed = if disable {
+                    settings.disabled_commands.insert(disabled)
+                } else {
+                    settings.disabled_commands.remove(&disabled)
+                };
+                Ok::<_, Infallible>(changed)
+            })
+            .await?;
+        if !changed {
+            return Ok(format!("Nothing changed for `{command}` in `{guild_id}`."));
+        }
+
+        info!(%guild_id, command, disable, "Changed disabled commands");
+        let action = if disable { "Disabled" } else { "Enabled" };
+        let log = format!("{action} `/{command}` in this server on behalf of the bot's admins.");
+        context.log_to_guild(guild_id, &log).await;
+        Ok(format!("{action} `{command}` in `{guild_id}`."))
+    }
+}
+
+impl CommandHandler for Command {
+    type Context = CommandContext;
+    type Response = ();
+    type Error = Error;
+
+    const ADMIN_ONLY: bool = true;
+
+    #[instrument(level = "info")]
+    async fn handle(self, context: Self::Context) -> Result<Self::Response, Self::Error> {
+        let content = self.run(&context).await?;
+        context
+            .reply(
+                InteractionResponseDataBuilder::new()
+                    .content(content)
+                    .flags(MessageFlags::EPHEMERAL)
+                    .build(),
+            )
+            .await
+            .map_err(TwilightError::from)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,197 @@
+// The autocomplete CommandModel derive generates code triggering this lint
+#![allow(clippy::needless_continue)]
+
+use super::{CommandHandler, is_configurable_command};
+use crate::commands::TwilightError;
+use crate::context::{CommandContext, GuildCommandContext};
+use crate::settings::{SettingError, SettingKey};
+use crate::storage::StorageError;
+use thiserror::Error;
+use tracing::instrument;
+use twilight_interactions::command::{AutocompleteValue, CommandModel, CreateCommand};
+use twilight_model::application::command::{CommandOptionChoice, CommandOptionChoiceValue};
+use twilight_model::channel::message::MessageFlags;
+use twilight_model::guild::Permissions;
+use twilight_util::builder::InteractionResponseDataBuilder;
+
+#[derive(Debug, CreateCommand, CommandModel)]
+#[command(
+    name = "config",
+    desc = "Show or change the bot's settings for this server.",
+    default_permissions = "required_permissions"
+)]
+pub enum Command {
+    #[command(name = "get")]
+    Get(Get),
+    #[command(name = "set")]
+    Set(Set),
+    #[command(name = "reset")]
+    Reset(Reset),
+}
+
+#[derive(Debug, CreateCommand, CommandModel)]
+#[command(name = "get", desc = "Show the server's settings.")]
+pub struct Get {
+    /// Setting to show, all settings are shown if left out
+    #[command(autocomplete = true)]
+    key: Option<String>,
+}
+
+#[derive(Debug, CreateCommand, CommandModel)]
+#[command(name = "set", desc = "Change a setting.")]
+pub struct Set {
+    /// Setting to change
+    #[command(autocomplete = true)]
+    key: String,
+    /// New value of the setting
+    value: String,
+}
+
+#[derive(Debug, CreateCommand, CommandModel)]
+#[command(name = "reset", desc = "Change a setting back to its default.")]
+pub struct Reset {
+    /// Setting to reset
+    #[command(autocomplete = true)]
+    key: String,
+}
+
+#[derive(Debug, CommandModel)]
+#[command(autocomplete = true)]
+pub enum Autocomplete {
+    #[command(name = "get")]
+    Get(KeyAutocomplete),
+    #[command(name = "set")]
+    Set(KeyAutocomplete),
+    #[command(name = "reset")]
+    Reset(KeyAutocomplete),
+}
+
+#[derive(Debug, CommandModel)]
+#[command(autocomplete = true)]
+pub struct KeyAutocomplete {
+    key: AutocompleteValue<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Error replying to interaction: {0}")]
+    Reply(#[from] TwilightError),
+    #[error("Error accessing the settings: {0}")]
+    Storage(#[from] StorageError),
+}
+
+fn required_permissions() -> Permissions {
+    Permissions::MANAGE_GUILD
+}
+
+impl Command {
+    /// Handles the subcommand, returning the reply.
+    async fn run(self, context: &GuildCommandContext) -> Result<String, Error> {
+        let settings_cache = context.state.guild_settings();
+        let guild_id = context.guild_id;
+        let result: Result<String, SettingError> = match self {
+            Command::Get(Get { key: None }) => {
+                return Ok(settings_cache.get(guild_id).await?.describe());
+            }
+            Command::Get(Get { key: Some(key) }) => {
+                let settings = settings_cache.get(guild_id).await?;
+                return Ok(SettingKey::parse(&key).map_or_else(
+                    |error| error.to_string(),
+                    |key| format!("**{}**: {}", key.name(), settings.get(key)),
+                ));
+            }
+            Command::Set(Set { key, value }) => {
+                settings_cache
+                    .update(guild_id, move |settings| {
+                        let key = SettingKey::parse(&key)?;
+                        settings.set(key, &value, is_configurable_command)?;
+                        Ok(format!("Set **{}** to {}.", key.name(), settings.get(key)))
+                    })
+                    .await?
+            }
+            Command::Reset(Reset { key }) => {
+                settings_cache
+                    .update(guild_id, move |settings| {
+                        let key = SettingKey::parse(&key)?;
+                        settings.reset(key);
+                        Ok(format!(
+                            "Reset **{}** to {}.",
+                            key.name(),
+                            settings.get(key)
+                        ))
+                    })
+                    .await?
+            }
+        };
+
+        match result {
+            Ok(reply) => {
+                let log = match context.interaction.author_id() {
+                    Some(user_id) => format!("<@{user_id}> changed the settings: {reply}"),
+                    None => format!("The settings were changed: {reply}"),
+                };
+                context.log_to_guild(guild_id, &log).await;
+                Ok(reply)
+            }
+            Err(error) => Ok(error.to_string()),
+        }
+    }
+}
+
+impl CommandHandler for Command {
+    type Context = GuildCommandContext;
+    type Response = ();
+    type Error = Error;
+
+    #[instrument(level = "info")]
+    async fn handle(self, context: Self::Context) -> Result<Self::Response, Self::Error> {
+        // Default permissions can be changed by the server, the bot's requirement can not
+        let permissions = context
+            .member
+            .permissions
+            .unwrap_or_else(Permissions::empty);
+        let content = if permissions.contains(Permissions::MANAGE_GUILD) {
+            self.run(&context).await?
+        } else {
+            "You need the Manage Server permission to use this command.".to_owned()
+        };
+
+        context
+            .reply(
+                InteractionResponseDataBuilder::new()
+                    .content(content)
+                    .flags(MessageFlags::EPHEMERAL)
+                    .build(),
+            )
+            .await
+            .map_err(TwilightError::from)?;
+        Ok(())
+    }
+}
+
+impl CommandHandler for Autocomplete {
+    type Context = CommandContext;
+    type Response = ();
+    type Error = Error;
+
+    #[instrument(level = "debug")]
+    async fn handle(self, context: Self::Context) -> Result<Self::Response, Self::Error> {
+        let (Autocomplete::Get(key) | Autocomplete::Set(key) | Autocomplete::Reset(key)) = self;
+        let AutocompleteValue::Focused(partial) = key.key else {
+            return Ok(());
+        };
+        let choices = SettingKey::ALL
+            .into_iter()
+            .filter(|key| key.name().starts_with(&partial.to_lowercase()))
+            .map(|key| CommandOptionChoice {
+                name: format!("{} - {}", key.name(), key.description()),
+                name_localizations: None,
+                value: CommandOptionChoiceValue::String(key.name().to_owned()),
+            });
+        context
+            .autocomplete(choices)
+            .await
+            .map_err(TwilightError::from)?;
+        Ok(())
+    }
+}
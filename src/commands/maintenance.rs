@@ -0,0 +1,54 @@
+use crate::commands::TwilightError;
+use crate::context::CommandContext;
+use crate::framework::CommandHandler;
+use std::sync::atomic::Ordering;
+use tracing::{info, instrument};
+use twilight_interactions::command::{CommandModel, CreateCommand};
+use twilight_util::builder::InteractionResponseDataBuilder;
+
+#[derive(Debug, CreateCommand, CommandModel)]
+#[command(
+    name = "maintenance",
+    desc = "Turn maintenance mode on or off, rejecting commands outside the admin server."
+)]
+pub struct Command {
+    /// Whether maintenance mode is on
+    enabled: bool,
+}
+
+pub type Error = TwilightError;
+
+impl CommandHandler for Command {
+    type Context = CommandContext;
+    type Response = ();
+    type Error = Error;
+
+    const ADMIN_ONLY: bool = true;
+
+    #[instrument(level = "info")]
+    async fn handle(self, context: Self::Context) -> Result<Self::Response, Self::Error> {
+        let was_enabled = context
+            .state
+            .maintenance
+            .swap(self.enabled, Ordering::AcqRel);
+        info!(
+            enabled = self.enabled,
+            was_enabled, "Changed maintenance mode"
+        );
+        let content = match (was_enabled, self.enabled) {
+            (false, true) => "Maintenance mode is on.",
+            (true, false) => "Maintenance mode is off.",
+            (true, true) => "Maintenance mode was on already.",
+            (false, false) => "Maintenance mode was off already.",
+        };
+
+        context
+            .reply(
+                InteractionResponseDataBuilder::new()
+                    .content(content)
+                    .build(),
+            )
+            .await?;
+        Ok(())
+    }
+}
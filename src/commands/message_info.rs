@@ -0,0 +1,52 @@
+use super::CommandHandler;
+use crate::commands::TwilightError;
+use crate::context::CommandContext;
+use crate::framework::{ContextMenuCommand, MessageTarget};
+use tracing::instrument;
+use twilight_model::channel::message::MessageFlags;
+use twilight_util::builder::InteractionResponseDataBuilder;
+
+#[derive(Debug)]
+pub struct Command {
+    target: MessageTarget,
+}
+
+pub type Error = TwilightError;
+
+impl ContextMenuCommand for Command {
+    const NAME: &'static str = "Message info";
+    type Target = MessageTarget;
+
+    fn from_target(target: Self::Target) -> Self {
+        Command { target }
+    }
+}
+
+impl CommandHandler for Command {
+    type Context = CommandContext;
+    type Response = ();
+    type Error = Error;
+
+    #[instrument(level = "info")]
+    async fn handle(self, context: Self::Context) -> Result<Self::Response, Self::Error> {
+        let message = self.target.message;
+        let content = format!(
+            "Sent by {} <t:{}:R>, {} characters, {} attachments, {} embeds.",
+            message.author.name,
+            message.timestamp.as_secs(),
+            message.content.chars().count(),
+            message.attachments.len(),
+            message.embeds.len(),
+        );
+
+        context
+            .reply(
+                InteractionResponseDataBuilder::new()
+                    .content(content)
+                    .flags(MessageFlags::EPHEMERAL)
+                    .build(),
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -1,5 +1,11 @@
-use crate::context::CommandContext;
-use crate::framework::{CommandHandler, FromCommandData, FromCommandDataError};
+use crate::confirm::Confirmable;
+use crate::context::{CommandContext, GuildCommandContext};
+use crate::extract::{CommandParts, FnHandler};
+use crate::framework::{
+    CommandHandler, ContextMenuCommand, ContextMenuTarget, FromCommandData, FromCommandDataError,
+    HandlerContext,
+};
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 use thiserror::Error;
@@ -7,14 +13,32 @@ use tracing::instrument;
 use twilight_http::client::InteractionClient;
 use twilight_http::response::DeserializeBodyError;
 use twilight_interactions::command::CreateCommand;
-use twilight_model::application::command::Command;
+use twilight_model::application::command::{Command, CommandOption, CommandType};
+use twilight_model::application::interaction::InteractionContextType;
 use twilight_model::application::interaction::application_command::CommandData;
+use twilight_model::channel::message::MessageFlags;
 use twilight_model::id::Id;
 use twilight_model::id::marker::GuildMarker;
+use twilight_model::oauth::ApplicationIntegrationType;
+use twilight_util::builder::InteractionResponseDataBuilder;
 
+mod announce;
+mod blocklist;
 mod command_a;
 mod command_b;
+mod counter;
+mod guild_commands;
+mod guild_config;
+mod maintenance;
+mod message_info;
+mod reload_commands;
+mod reload_config;
+mod remind;
+mod restart;
+mod server;
 mod shutdown;
+mod user_info;
+mod whoami;
 
 #[derive(Debug, Error)]
 pub enum TwilightError {
@@ -24,6 +48,48 @@ pub enum TwilightError {
     Model(#[from] DeserializeBodyError),
 }
 
+/// Replies to commands used outside the admin guild, returning whether the command may run.
+async fn require_admin_guild(context: &CommandContext) -> Result<bool, TwilightError> {
+    if context.is_admin_guild() {
+        return Ok(true);
+    }
+
+    context
+        .reply(
+            InteractionResponseDataBuilder::new()
+                .content("This command is only available in the admin server.")
+                .flags(MessageFlags::EPHEMERAL)
+                .build(),
+        )
+        .await?;
+    Ok(false)
+}
+
+/// Whether the global command can be disabled or given a cooldown in a guild, `/config` itself can not.
+fn is_configurable_command(name: &str) -> bool {
+    name != guild_config::Command::NAME
+        && Commands::global_commands()
+            .iter()
+            .any(|command| command.name == name)
+}
+
+/// Checks the command may be used here and asks for confirmation if it requires it,
+/// returning the context to handle it with.
+async fn prepare<TCommand, TContext>(context: TContext) -> Result<Option<TContext>, TwilightError>
+where
+    TCommand: CommandHandler<Context = TContext>,
+    TContext: Confirmable,
+{
+    if TCommand::ADMIN_ONLY && !require_admin_guild(context.command_context()).await? {
+        return Ok(None);
+    }
+
+    match TCommand::CONFIRMATION {
+        Some(prompt) => context.confirm(prompt).await,
+        None => Ok(Some(context)),
+    }
+}
+
 macro_rules! commands_collection {
     (Create collection $collection_name:ident
     with error type $error_name:ident
@@ -33,20 +99,45 @@ macro_rules! commands_collection {
         $($command_name:ident
         at $command_type:path;
         with error type $command_error_type:path,)*
+    }
+    from functions: {
+        $($fn_command_name:ident
+        at $fn_handler:path;
+        with options $fn_options_type:path;
+        with error type $fn_command_error_type:path,)*
+    }
+    from context menus: {
+        $($menu_command_name:ident
+        at $menu_command_type:path;
+        with error type $menu_command_error_type:path,)*
     }) => {
         #[derive(Debug)]
         $vis enum $error_name {
+            Confirmation(TwilightError),
             $($command_name($command_error_type),
             )*
+            $($fn_command_name($fn_command_error_type),
+            )*
+            $($menu_command_name($menu_command_error_type),
+            )*
         }
 
         impl Error for $error_name {}
         impl Display for $error_name {
             fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
                 match self {
+                    $error_name::Confirmation(inner) => {
+                        write!(f, "Confirmation had error: {inner}")
+                    }
                     $($error_name::$command_name(inner) => {
                         write!(f, "Command {} had error: {inner}", stringify!($command_name))
                     })*
+                    $($error_name::$fn_command_name(inner) => {
+                        write!(f, "Command {} had error: {inner}", stringify!($fn_command_name))
+                    })*
+                    $($error_name::$menu_command_name(inner) => {
+                        write!(f, "Command {} had error: {inner}", stringify!($menu_command_name))
+                    })*
                 }
             }
         }
@@ -55,6 +146,132 @@ macro_rules! commands_collection {
         $vis enum $collection_name {
             $($command_name($command_type),
             )*
+            $($fn_command_name($fn_options_type),
+            )*
+            // Targets are large compared to slash command options
+            $($menu_command_name(Box<$menu_command_type>),
+            )*
+        }
+
+        impl $collection_name {
+            /// The commands as registered, restricted to where their context can be created.
+            fn create_commands() -> Vec<Command> {
+                vec![
+                    $(<$context>::register(<$command_type>::create_command().into()),
+                    )*
+                    $(<$context>::register(<$fn_options_type>::create_command().into()),
+                    )*
+                    $(<$context>::register(<$menu_command_type>::create_command()),
+                    )*
+                ]
+            }
+        }
+
+        impl FromCommandData for $collection_name {
+            #[instrument(level = "trace")]
+            fn from_command_data(data: Box<CommandData>) -> Result<Self, FromCommandDataError> {
+                // Names are only unique per command type
+                match (data.kind, &*data.name) {
+                    $((CommandType::ChatInput, <$command_type>::NAME) => {
+                        Ok(
+                            $collection_name
+                                ::$command_name(<$command_type>::from_command_data(data)?)
+                        )
+                    })*
+                    $((CommandType::ChatInput, <$fn_options_type>::NAME) => {
+                        Ok(
+                            $collection_name
+                                ::$fn_command_name(<$fn_options_type>::from_command_data(data)?)
+                        )
+                    })*
+                    $((kind, name)
+                        if kind == <<$menu_command_type as ContextMenuCommand>::Target>::KIND
+                            && name == <$menu_command_type>::NAME =>
+                    {
+                        let target = ContextMenuTarget::from_command_data(&data)?;
+                        Ok(
+                            $collection_name
+                                ::$menu_command_name(Box::new(<$menu_command_type>::from_target(target)))
+                        )
+                    })*
+                    _ => Err(FromCommandDataError::UnknownCommand(data)),
+                }
+            }
+        }
+
+        impl CommandHandler for $collection_name {
+            type Context = $context;
+            type Response = ();
+            type Error = $error_name;
+
+            #[instrument(level = "debug")]
+            async fn handle(
+                self,
+                context: Self::Context,
+            ) -> Result<Self::Response, Self::Error> {
+                match self {
+                    $($collection_name::$command_name(command) => {
+                        let Some(context) = prepare::<$command_type, _>(context)
+                            .await
+                            .map_err($error_name::Confirmation)?
+                        else {
+                            return Ok(());
+                        };
+                        command.handle(context).await.map_err($error_name::$command_name)
+                    })*
+                    $($collection_name::$fn_command_name(options) => FnHandler::call(
+                        $fn_handler,
+                        CommandParts::new(context, options),
+                    )
+                    .await
+                    .map_err($error_name::$fn_command_name),
+                    )*
+                    $($collection_name::$menu_command_name(command) => {
+                        let Some(context) = prepare::<$menu_command_type, _>(context)
+                            .await
+                            .map_err($error_name::Confirmation)?
+                        else {
+                            return Ok(());
+                        };
+                        command.handle(context).await.map_err($error_name::$menu_command_name)
+                    })*
+                }
+            }
+        }
+    };
+}
+macro_rules! autocomplete_collection {
+    (Create collection $collection_name:ident
+    with error type $error_name:ident
+    with visibility $vis:vis
+    with context $context:ty;
+    from commands: {
+        $($command_name:ident
+        at $command_type:path;
+        with autocomplete $autocomplete_type:path;
+        with error type $command_error_type:path,)*
+    }) => {
+        #[derive(Debug)]
+        $vis enum $error_name {
+            $($command_name($command_error_type),
+            )*
+        }
+
+        impl Error for $error_name {}
+        impl Display for $error_name {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    $($error_name::$command_name(inner) => {
+                        write!(f, "Autocomplete {} had error: {inner}", stringify!($command_name))
+                    })*
+                }
+            }
+        }
+
+        #[derive(Debug)]
+        $vis enum $collection_name {
+            $($command_name($autocomplete_type),
+            )*
         }
 
         impl FromCommandData for $collection_name {
@@ -64,7 +281,7 @@ macro_rules! commands_collection {
                     $(<$command_type>::NAME => {
                         Ok(
                             $collection_name
-                                ::$command_name(<$command_type>::from_command_data(data)?)
+                                ::$command_name(<$autocomplete_type>::from_command_data(data)?)
                         )
                     })*
                     _ => Err(FromCommandDataError::UnknownCommand(data)),
@@ -83,7 +300,7 @@ macro_rules! commands_collection {
                 context: Self::Context,
             ) -> Result<Self::Response, Self::Error> {
                 match self {
-                    $($collection_name::$command_name(command) => command
+                    $($collection_name::$command_name(autocomplete) => autocomplete
                         .handle(context)
                         .await
                         .map_err($error_name::$command_name),
@@ -101,34 +318,319 @@ commands_collection! {
     from commands: {
         A at command_a::Command; with error type command_a::Error,
         B at command_b::Command; with error type command_b::Error,
+        Counter at counter::Command; with error type counter::Error,
+        Remind at remind::Command; with error type remind::Error,
+    }
+    from functions: {
+        WhoAmI at whoami::whoami; with options whoami::Command; with error type whoami::Error,
+    }
+    from context menus: {
+        UserInfo at user_info::Command; with error type user_info::Error,
+        MessageInfo at message_info::Command; with error type message_info::Error,
+    }
+}
+commands_collection! {
+    Create collection GuildCommands
+    with error type GuildCommandError
+    with visibility pub
+    with context GuildCommandContext;
+    from commands: {
+        Server at server::Command; with error type server::Error,
+        Config at guild_config::Command; with error type guild_config::Error,
+    }
+    from functions: {}
+    from context menus: {}
+}
+commands_collection! {
+    Create collection AdminCommands
+    with error type AdminCommandError
+    with visibility pub
+    with context CommandContext;
+    from commands: {
         Shutdown at shutdown::Command; with error type shutdown::Error,
+        Restart at restart::Command; with error type restart::Error,
+        ReloadCommands at reload_commands::Command; with error type reload_commands::Error,
+        ReloadConfig at reload_config::Command; with error type reload_config::Error,
+        GuildCommands at guild_commands::Command; with error type guild_commands::Error,
+        Maintenance at maintenance::Command; with error type maintenance::Error,
+        Blocklist at blocklist::Command; with error type blocklist::Error,
+        Announce at announce::Command; with error type announce::Error,
+    }
+    from functions: {}
+    from context menus: {}
+}
+
+autocomplete_collection! {
+    Create collection Autocompletes
+    with error type AutocompleteError
+    with visibility pub
+    with context CommandContext;
+    from commands: {
+        B at command_b::Command; with autocomplete command_b::Autocomplete; with error type command_b::Error,
+        Config at guild_config::Command; with autocomplete guild_config::Autocomplete; with error type guild_config::Error,
+        Remind at remind::Command; with autocomplete remind::Autocomplete; with error type remind::Error,
     }
 }
 
 impl Commands {
-    fn global_commands() -> [Command; 2] {
-        [
-            command_a::Command::create_command().into(),
-            command_b::Command::create_command().into(),
-        ]
+    fn global_commands() -> Vec<Command> {
+        let mut commands = Commands::create_commands();
+        commands.extend(GuildCommands::create_commands());
+        commands
     }
 
-    fn admin_guild_commands() -> [Command; 1] {
-        [shutdown::Command::create_command().into()]
+    fn admin_guild_commands() -> Vec<Command> {
+        AdminCommands::create_commands()
     }
 
+    /// Registers the global and admin guild commands, returning what changed.
     #[instrument(level = "info", skip(client))]
     pub async fn update_commands(
         client: &InteractionClient<'_>,
         admin_guild_id: Id<GuildMarker>,
-    ) -> Result<(), twilight_http::Error> {
+    ) -> Result<CommandsDiff, TwilightError> {
+        let registered = client.global_commands().await?.models().await?;
         let global_commands = Self::global_commands();
+        let mut diff = CommandsDiff::new(&registered, &global_commands);
         client.set_global_commands(&global_commands).await?;
 
+        let registered = client
+            .guild_commands(admin_guild_id)
+            .await?
+            .models()
+            .await?;
         let admin_commands = Self::admin_guild_commands();
+        diff.extend(CommandsDiff::new(&registered, &admin_commands));
         client
             .set_guild_commands(admin_guild_id, &admin_commands)
             .await?;
+        Ok(diff)
+    }
+}
+
+/// Commands added, removed and changed by [`Commands::update_commands`].
+#[derive(Clone, Default, Debug)]
+pub struct CommandsDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+impl CommandsDiff {
+    fn new(registered: &[Command], commands: &[Command]) -> Self {
+        // Names are only unique per command type
+        let key = |command: &Command| (command.kind, command.name.clone());
+        let registered_by_key: HashMap<_, _> = registered
+            .iter()
+            .map(|command| (key(command), command))
+            .collect();
+        let keys: HashSet<_> = commands.iter().map(key).collect();
+
+        let mut diff = CommandsDiff::default();
+        for command in commands {
+            match registered_by_key.get(&key(command)) {
+                None => diff.added.push(command.name.clone()),
+                Some(old) if !same_command(old, command) => diff.changed.push(command.name.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.removed = registered
+            .iter()
+            .filter(|command| !keys.contains(&key(command)))
+            .map(|command| command.name.clone())
+            .collect();
+        diff
+    }
+
+    fn extend(&mut self, other: CommandsDiff) {
+        self.added.extend(other.added);
+        self.removed.extend(other.removed);
+        self.changed.extend(other.changed);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl Display for CommandsDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "No changes");
+        }
+        let lines = self.added.iter().map(|name| ("+", name));
+        let lines = lines.chain(self.removed.iter().map(|name| ("-", name)));
+        let lines = lines.chain(self.changed.iter().map(|name| ("~", name)));
+        for (index, (marker, name)) in lines.enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{marker} {name}")?;
+        }
         Ok(())
     }
 }
+
+/// Compares the parts of the commands defined by the bot, ignoring what Discord fills in.
+fn same_command(registered: &Command, command: &Command) -> bool {
+    registered.description == command.description
+        && registered.default_member_permissions == command.default_member_permissions
+        && registered.nsfw.unwrap_or(false) == command.nsfw.unwrap_or(false)
+        && contexts(registered) == contexts(command)
+        && integration_types(registered) == integration_types(command)
+        && same_localizations(
+            registered.name_localizations.as_ref(),
+            command.name_localizations.as_ref(),
+        )
+        && same_localizations(
+            registered.description_localizations.as_ref(),
+            command.description_localizations.as_ref(),
+        )
+        && same_options(&registered.options, &command.options)
+}
+
+/// Contexts the command can be used in, Discord allows all of them if none are set.
+fn contexts(command: &Command) -> HashSet<InteractionContextType> {
+    command.contexts.as_ref().map_or_else(
+        || {
+            HashSet::from([
+                InteractionContextType::Guild,
+                InteractionContextType::BotDm,
+                InteractionContextType::PrivateChannel,
+            ])
+        },
+        |contexts| contexts.iter().copied().collect(),
+    )
+}
+
+/// Installations the command is available in, Discord uses guild installs if none are set.
+fn integration_types(command: &Command) -> HashSet<ApplicationIntegrationType> {
+    command.integration_types.as_ref().map_or_else(
+        || HashSet::from([ApplicationIntegrationType::GuildInstall]),
+        |integration_types| integration_types.iter().copied().collect(),
+    )
+}
+
+/// Compares localizations, treating missing ones like empty ones.
+fn same_localizations(
+    registered: Option<&HashMap<String, String>>,
+    localizations: Option<&HashMap<String, String>>,
+) -> bool {
+    let non_empty = |localizations: &&HashMap<String, String>| !localizations.is_empty();
+    registered.filter(non_empty) == localizations.filter(non_empty)
+}
+
+fn same_options(registered: &[CommandOption], options: &[CommandOption]) -> bool {
+    registered.len() == options.len()
+        && registered.iter().zip(options).all(|(registered, option)| {
+            registered.name == option.name
+                && registered.kind == option.kind
+                && registered.description == option.description
+                && registered.required.unwrap_or(false) == option.required.unwrap_or(false)
+                && registered.autocomplete.unwrap_or(false) == option.autocomplete.unwrap_or(false)
+                && registered.choices == option.choices
+                && same_localizations(
+                    registered.name_localizations.as_ref(),
+                    option.name_localizations.as_ref(),
+                )
+                && same_localizations(
+                    registered.description_localizations.as_ref(),
+                    option.description_localizations.as_ref(),
+                )
+                && same_options(
+                    registered.options.as_deref().unwrap_or_default(),
+                    option.options.as_deref().unwrap_or_default(),
+                )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use twilight_util::builder::command::CommandBuilder;
+
+    fn command(name: &str, description: &str) -> Command {
+        CommandBuilder::new(name, description, CommandType::ChatInput).build()
+    }
+
+    fn diff(
+        registered: &[Command],
+        commands: &[Command],
+    ) -> (Vec<String>, Vec<String>, Vec<String>) {
+        let diff = CommandsDiff::new(registered, commands);
+        (diff.added, diff.removed, diff.changed)
+    }
+
+    #[test]
+    fn unchanged() {
+        let commands = [command("a", "A"), command("b", "B")];
+        assert!(CommandsDiff::new(&commands, &commands).is_empty());
+    }
+
+    #[test]
+    fn added_removed_and_changed() {
+        let registered = [command("a", "A"), command("b", "B")];
+        let commands = [command("b", "Changed"), command("c", "C")];
+        assert_eq!(
+            diff(&registered, &commands),
+            (
+                vec!["c".to_owned()],
+                vec!["a".to_owned()],
+                vec!["b".to_owned()]
+            )
+        );
+    }
+
+    #[test]
+    fn same_name_of_another_kind() {
+        let registered = [command("a", "A")];
+        let commands = [CommandBuilder::new("a", "", CommandType::User).build()];
+        assert_eq!(
+            diff(&registered, &commands),
+            (vec!["a".to_owned()], vec!["a".to_owned()], vec![])
+        );
+    }
+
+    #[test]
+    fn default_contexts_and_integration_types() {
+        let registered = [command("a", "A")];
+        let defaults = CommandBuilder::new("a", "A", CommandType::ChatInput)
+            .contexts([
+                InteractionContextType::PrivateChannel,
+                InteractionContextType::Guild,
+                InteractionContextType::BotDm,
+            ])
+            .integration_types([ApplicationIntegrationType::GuildInstall])
+            .build();
+        assert!(CommandsDiff::new(&registered, &[defaults]).is_empty());
+
+        let guild_only = CommandBuilder::new("a", "A", CommandType::ChatInput)
+            .contexts([InteractionContextType::Guild])
+            .build();
+        assert_eq!(diff(&registered, &[guild_only]).2, vec!["a".to_owned()]);
+
+        let user_install = CommandBuilder::new("a", "A", CommandType::ChatInput)
+            .integration_types([ApplicationIntegrationType::UserInstall])
+            .build();
+        assert_eq!(diff(&registered, &[user_install]).2, vec!["a".to_owned()]);
+    }
+
+    #[test]
+    fn localizations() {
+        let registered = [command("a", "A")];
+        let empty = CommandBuilder::new("a", "A", CommandType::ChatInput)
+            .name_localizations([] as [(String, String); 0])
+            .build();
+        assert!(CommandsDiff::new(&registered, &[empty]).is_empty());
+
+        let named = CommandBuilder::new("a", "A", CommandType::ChatInput)
+            .name_localizations([("de", "b")])
+            .build();
+        assert_eq!(diff(&registered, &[named]).2, vec!["a".to_owned()]);
+
+        let described = CommandBuilder::new("a", "A", CommandType::ChatInput)
+            .description_localizations([("de", "B")])
+            .build();
+        assert_eq!(diff(&registered, &[described]).2, vec!["a".to_owned()]);
+    }
+}
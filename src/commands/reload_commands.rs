@@ -0,0 +1,78 @@
+use crate::commands::{Commands, TwilightError};
+use crate::context::{AdminGuildId, CommandContext};
+use crate::framework::CommandHandler;
+use crate::retry;
+use std::future::IntoFuture;
+use thiserror::Error;
+use tracing::{info, instrument};
+use twilight_interactions::command::{CommandModel, CreateCommand};
+use twilight_model::channel::message::MessageFlags;
+use twilight_model::http::interaction::InteractionResponseType;
+use twilight_util::builder::InteractionResponseDataBuilder;
+
+#[derive(Debug, CreateCommand, CommandModel)]
+#[command(name = "reload-commands", desc = "Register the bot's commands again.")]
+pub struct Command;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Admin guild id is missing from the state")]
+    MissingAdminGuild,
+    #[error("Error updating commands: {0}")]
+    Update(TwilightError),
+    #[error("Error replying to interaction: {0}")]
+    Reply(#[from] TwilightError),
+}
+
+impl CommandHandler for Command {
+    type Context = CommandContext;
+    type Response = ();
+    type Error = Error;
+
+    const ADMIN_ONLY: bool = true;
+
+    #[instrument(level = "info")]
+    async fn handle(self, context: Self::Context) -> Result<Self::Response, Self::Error> {
+        let Some(&AdminGuildId(admin_guild_id)) = context.state.extensions.get::<AdminGuildId>()
+        else {
+            return Err(Error::MissingAdminGuild);
+        };
+
+        // Fetching and replacing the commands can take longer than the response deadline
+        context
+            .respond(
+                InteractionResponseType::DeferredChannelMessageWithSource,
+                Some(
+                    InteractionResponseDataBuilder::new()
+                        .flags(MessageFlags::EPHEMERAL)
+                        .build(),
+                ),
+            )
+            .await
+            .map_err(TwilightError::from)?;
+
+        let client = context.state.interaction_client();
+        let result = Commands::update_commands(&client, admin_guild_id).await;
+        let content = match &result {
+            Ok(diff) => {
+                info!(%diff, "Reloaded commands");
+                format!("Commands reloaded.\n```diff\n{diff}\n```")
+            }
+            Err(error) => format!("Reloading commands failed: {error}"),
+        };
+
+        context
+            .state
+            .retry_policy
+            .retry(retry::token_deadline(context.interaction.id), || {
+                client
+                    .update_response(&context.interaction.token)
+                    .content(Some(&content))
+                    .into_future()
+            })
+            .await
+            .map_err(TwilightError::from)?;
+
+        result.map(drop).map_err(Error::Update)
+    }
+}
@@ -0,0 +1,49 @@
+use crate::commands::TwilightError;
+use crate::context::CommandContext;
+use crate::framework::CommandHandler;
+use thiserror::Error;
+use tracing::{instrument, warn};
+use twilight_interactions::command::{CommandModel, CreateCommand};
+use twilight_model::channel::message::MessageFlags;
+use twilight_util::builder::InteractionResponseDataBuilder;
+
+#[derive(Debug, CreateCommand, CommandModel)]
+#[command(name = "reload-config", desc = "Read the bot's configuration again.")]
+pub struct Command;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Error replying to interaction: {0}")]
+    Reply(#[from] TwilightError),
+}
+
+impl CommandHandler for Command {
+    type Context = CommandContext;
+    type Response = ();
+    type Error = Error;
+
+    const ADMIN_ONLY: bool = true;
+
+    #[instrument(level = "info")]
+    async fn handle(self, context: Self::Context) -> Result<Self::Response, Self::Error> {
+        let content = match context.state.reload_config() {
+            Ok(()) => "Configuration reloaded, the audit settings apply immediately.".to_owned(),
+            Err(error) => {
+                warn!(%error, "Could not reload config");
+                format!("Configuration was not reloaded: {error}")
+            }
+        };
+
+        context
+            .reply(
+                InteractionResponseDataBuilder::new()
+                    .content(content)
+                    .flags(MessageFlags::EPHEMERAL)
+                    .build(),
+            )
+            .await
+            .map_err(TwilightError::from)?;
+
+        Ok(())
+    }
+}
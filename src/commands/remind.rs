@@ -0,0 +1,204 @@
+// The autocomplete CommandModel derive generates code triggering this lint
+#![allow(clippy::needless_continue)]
+
+use super::CommandHandler;
+use crate::commands::TwilightError;
+use crate::context::CommandContext;
+use crate::jobs::reminder::{self, Reminder};
+use crate::scheduler::{JobId, SchedulerError};
+use crate::storage::StorageError;
+use crate::util::{join_lines, truncate};
+use crate::when;
+use chrono::Utc;
+use thiserror::Error;
+use tracing::instrument;
+use twilight_interactions::command::{AutocompleteValue, CommandModel, CreateCommand};
+use twilight_model::application::command::{CommandOptionChoice, CommandOptionChoiceValue};
+use twilight_model::channel::message::MessageFlags;
+use twilight_util::builder::InteractionResponseDataBuilder;
+
+/// Longest name Discord accepts for an autocomplete choice.
+const MAX_CHOICE_NAME: usize = 100;
+/// Longest part of a reminder's message shown by `/remind list`.
+const MAX_LISTED_MESSAGE: usize = 150;
+
+#[derive(Debug, CreateCommand, CommandModel)]
+#[command(name = "remind", desc = "Get reminded of something later.")]
+pub enum Command {
+    #[command(name = "set")]
+    Set(Set),
+    #[command(name = "list")]
+    List(List),
+    #[command(name = "cancel")]
+    Cancel(Cancel),
+}
+
+#[derive(Debug, CreateCommand, CommandModel)]
+#[command(name = "set", desc = "Set a reminder.")]
+pub struct Set {
+    /// When to remind you, e.g. 1h30m or tomorrow 9am (UTC)
+    #[command(rename = "in", max_length = 50)]
+    when: String,
+    /// What to remind you of
+    #[command(max_length = 1000)]
+    message: String,
+    /// Whether to send the reminder by direct message instead of in this channel
+    dm: Option<bool>,
+}
+
+#[derive(Debug, CreateCommand, CommandModel)]
+#[command(name = "list", desc = "Show your pending reminders.")]
+pub struct List;
+
+#[derive(Debug, CreateCommand, CommandModel)]
+#[command(name = "cancel", desc = "Cancel a pending reminder.")]
+pub struct Cancel {
+    /// Reminder to cancel
+    #[command(autocomplete = true)]
+    reminder: String,
+}
+
+#[derive(Debug, CommandModel)]
+#[command(autocomplete = true)]
+pub enum Autocomplete {
+    #[command(name = "cancel")]
+    Cancel(CancelAutocomplete),
+}
+
+#[derive(Debug, CommandModel)]
+#[command(autocomplete = true)]
+pub struct CancelAutocomplete {
+    reminder: AutocompleteValue<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Error replying to interaction: {0}")]
+    Reply(#[from] TwilightError),
+    #[error("Error accessing the reminders: {0}")]
+    Storage(#[from] StorageError),
+    #[error("Error scheduling the reminder: {0}")]
+    Schedule(#[from] SchedulerError),
+}
+
+impl Command {
+    /// Handles the subcommand, returning the reply.
+    async fn run(self, context: &CommandContext) -> Result<String, Error> {
+        let Some(user_id) = context.interaction.author_id() else {
+            return Ok("Could not tell who used the command.".to_owned());
+        };
+        let state = &context.state;
+        match self {
+            Command::Set(Set { when, message, dm }) => {
+                let due_at = match when::parse(&when, Utc::now()) {
+                    Ok(due_at) => due_at,
+                    Err(error) => return Ok(error.to_string()),
+                };
+                let channel_id = context
+                    .interaction
+                    .channel
+                    .as_ref()
+                    .map(|channel| channel.id);
+                let reminder = Reminder {
+                    user_id,
+                    channel_id: channel_id.filter(|_| !dm.unwrap_or(false)),
+                    message,
+                };
+                if let Err(error) = reminder::schedule(state, &reminder, due_at).await? {
+                    return Ok(error.to_string());
+                }
+                let timestamp = due_at.timestamp();
+                Ok(format!(
+                    "I'll remind you <t:{timestamp}:R>, at <t:{timestamp}:f>."
+                ))
+            }
+            Command::List(List) => {
+                let pending = reminder::pending(state.storage(), user_id).await?;
+                if pending.is_empty() {
+                    return Ok("You have no pending reminders.".to_owned());
+                }
+                let lines: Vec<_> = pending
+                    .iter()
+                    .map(|reminder| {
+                        format!(
+                            "<t:{}:R>: {}",
+                            reminder.due_at.timestamp(),
+                            truncate(&reminder.message, MAX_LISTED_MESSAGE)
+                        )
+                    })
+                    .collect();
+                Ok(join_lines(&lines))
+            }
+            Command::Cancel(Cancel { reminder }) => {
+                let Ok(id) = reminder.parse().map(JobId) else {
+                    return Ok("Pick one of your reminders from the list.".to_owned());
+                };
+                if reminder::cancel(state, user_id, id).await? {
+                    Ok("Cancelled the reminder.".to_owned())
+                } else {
+                    Ok("That reminder is not pending anymore.".to_owned())
+                }
+            }
+        }
+    }
+}
+
+impl CommandHandler for Command {
+    type Context = CommandContext;
+    type Response = ();
+    type Error = Error;
+
+    #[instrument(level = "info")]
+    async fn handle(self, context: Self::Context) -> Result<Self::Response, Self::Error> {
+        let content = self.run(&context).await?;
+        context
+            .reply(
+                InteractionResponseDataBuilder::new()
+                    .content(content)
+                    .flags(MessageFlags::EPHEMERAL)
+                    .build(),
+            )
+            .await
+            .map_err(TwilightError::from)?;
+        Ok(())
+    }
+}
+
+impl CommandHandler for Autocomplete {
+    type Context = CommandContext;
+    type Response = ();
+    type Error = Error;
+
+    #[instrument(level = "debug")]
+    async fn handle(self, context: Self::Context) -> Result<Self::Response, Self::Error> {
+        let Autocomplete::Cancel(cancel) = self;
+        let AutocompleteValue::Focused(partial) = cancel.reminder else {
+            return Ok(());
+        };
+        let Some(user_id) = context.interaction.author_id() else {
+            return Ok(());
+        };
+        let partial = partial.to_lowercase();
+        let choices = reminder::pending(context.state.storage(), user_id)
+            .await?
+            .into_iter()
+            .filter(|reminder| reminder.message.to_lowercase().contains(&partial))
+            .map(|reminder| CommandOptionChoice {
+                name: truncate(
+                    &format!(
+                        "{} · {}",
+                        reminder.due_at.format("%Y-%m-%d %H:%M UTC"),
+                        reminder.message
+                    ),
+                    MAX_CHOICE_NAME,
+                ),
+                name_localizations: None,
+                value: CommandOptionChoiceValue::String(reminder.id.0.to_string()),
+            });
+        context
+            .autocomplete(choices)
+            .await
+            .map_err(TwilightError::from)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,45 @@
+use crate::commands::TwilightError;
+use crate::context::CommandContext;
+use crate::framework::CommandHandler;
+use thiserror::Error;
+use tracing::instrument;
+use twilight_gateway::error::ChannelError;
+use twilight_interactions::command::{CommandModel, CreateCommand};
+use twilight_util::builder::InteractionResponseDataBuilder;
+
+#[derive(Debug, CreateCommand, CommandModel)]
+#[command(name = "restart", desc = "Restart the bot.")]
+pub struct Command;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("One or more channel errors occurred, restart might be incomplete: {0:?}")]
+    Channel(Vec<ChannelError>),
+    #[error("Error replying to interaction: {0}")]
+    Reply(#[from] TwilightError),
+}
+
+impl CommandHandler for Command {
+    type Context = CommandContext;
+    type Response = ();
+    type Error = Error;
+
+    const ADMIN_ONLY: bool = true;
+    const CONFIRMATION: Option<&'static str> = Some("Restart the bot for everyone?");
+
+    #[instrument(level = "info")]
+    async fn handle(self, context: Self::Context) -> Result<Self::Response, Self::Error> {
+        context.state.send_restart().map_err(Error::Channel)?;
+
+        context
+            .reply(
+                InteractionResponseDataBuilder::new()
+                    .content("Restart initiated, finishing running commands first.")
+                    .build(),
+            )
+            .await
+            .map_err(TwilightError::from)?;
+
+        Ok(())
+    }
+}
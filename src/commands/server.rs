@@ -0,0 +1,54 @@
+use super::CommandHandler;
+use crate::commands::TwilightError;
+use crate::context::GuildCommandContext;
+use std::fmt::Write;
+use tracing::instrument;
+use twilight_interactions::command::{CommandModel, CreateCommand};
+use twilight_model::channel::message::MessageFlags;
+use twilight_util::builder::InteractionResponseDataBuilder;
+
+#[derive(Debug, CreateCommand, CommandModel)]
+#[command(name = "server", desc = "Show what the bot knows about this server.")]
+pub struct Command;
+
+pub type Error = TwilightError;
+
+impl CommandHandler for Command {
+    type Context = GuildCommandContext;
+    type Response = ();
+    type Error = Error;
+
+    #[instrument(level = "info")]
+    async fn handle(self, context: Self::Context) -> Result<Self::Response, Self::Error> {
+        // Cache references must not be held across awaits
+        let guild = context
+            .cache()
+            .and_then(|cache| cache.guild(context.guild_id))
+            .map(|guild| (guild.name().to_owned(), guild.member_count()));
+        let mut content = match &guild {
+            Some((name, _)) => format!("This is **{name}** (`{}`)", context.guild_id),
+            None => format!("This is the server `{}`", context.guild_id),
+        };
+        match &context.channel.name {
+            Some(name) => _ = write!(content, ", in #{name}."),
+            None => _ = write!(content, ", in the channel `{}`.", context.channel.id),
+        }
+        if let Some(joined_at) = context.member.joined_at {
+            _ = write!(content, "\nYou joined <t:{}:R>", joined_at.as_secs());
+        }
+        _ = write!(content, " and have {} roles.", context.member.roles.len());
+        if let Some((_, Some(member_count))) = guild {
+            _ = write!(content, "\nThe server has {member_count} members.");
+        }
+
+        context
+            .reply(
+                InteractionResponseDataBuilder::new()
+                    .content(content)
+                    .flags(MessageFlags::EPHEMERAL)
+                    .build(),
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -25,6 +25,9 @@ impl CommandHandler for Command {
     type Response = ();
     type Error = Error;
 
+    const ADMIN_ONLY: bool = true;
+    const CONFIRMATION: Option<&'static str> = Some("Shut down the bot for everyone?");
+
     #[instrument(level = "info")]
     async fn handle(self, context: Self::Context) -> Result<Self::Response, Self::Error> {
         context.state.send_shutdown().map_err(Error::Channel)?;
@@ -0,0 +1,58 @@
+use super::CommandHandler;
+use crate::commands::TwilightError;
+use crate::context::CommandContext;
+use crate::framework::{ContextMenuCommand, UserTarget};
+use std::fmt::Write;
+use tracing::instrument;
+use twilight_model::channel::message::MessageFlags;
+use twilight_util::builder::InteractionResponseDataBuilder;
+
+#[derive(Debug)]
+pub struct Command {
+    target: UserTarget,
+}
+
+pub type Error = TwilightError;
+
+impl ContextMenuCommand for Command {
+    const NAME: &'static str = "User info";
+    type Target = UserTarget;
+
+    fn from_target(target: Self::Target) -> Self {
+        Command { target }
+    }
+}
+
+impl CommandHandler for Command {
+    type Context = CommandContext;
+    type Response = ();
+    type Error = Error;
+
+    #[instrument(level = "info")]
+    async fn handle(self, context: Self::Context) -> Result<Self::Response, Self::Error> {
+        let UserTarget { user, member } = self.target;
+        let mut content = format!("{} (`{}`)", user.name, user.id);
+        if user.bot {
+            content.push_str(", a bot");
+        }
+        if let Some(member) = member {
+            if let Some(nick) = member.nick {
+                _ = write!(content, ", known here as {nick}");
+            }
+            if let Some(joined_at) = member.joined_at {
+                _ = write!(content, ", joined <t:{}:R>", joined_at.as_secs());
+            }
+        }
+        content.push('.');
+
+        context
+            .reply(
+                InteractionResponseDataBuilder::new()
+                    .content(content)
+                    .flags(MessageFlags::EPHEMERAL)
+                    .build(),
+            )
+            .await?;
+        Ok(())
+    }
+}
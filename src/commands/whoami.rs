@@ -0,0 +1,64 @@
+use crate::commands::TwilightError;
+use crate::components::dismiss;
+use crate::context::{AdminGuildId, CommandContext, ReceivedAt};
+use crate::extract::{
+    Extension, FnCommandError, FromState, GuildId, Locale, Member, Options, User,
+};
+use std::fmt::Write;
+use tracing::instrument;
+use twilight_interactions::command::{CommandModel, CreateCommand};
+use twilight_model::channel::message::MessageFlags;
+use twilight_util::builder::InteractionResponseDataBuilder;
+
+#[derive(Debug, CreateCommand, CommandModel)]
+#[command(name = "whoami", desc = "Show what the bot knows about you.")]
+pub struct Command {
+    /// Only show the answer to you
+    ephemeral: Option<bool>,
+}
+
+pub type Error = FnCommandError<TwilightError>;
+
+#[allow(clippy::too_many_arguments, reason = "every argument is an extractor")]
+#[instrument(level = "info", skip(context))]
+pub async fn whoami(
+    context: CommandContext,
+    Options(options): Options<Command>,
+    User(user): User,
+    member: Option<Member>,
+    guild_id: Option<GuildId>,
+    Locale(locale): Locale,
+    FromState(AdminGuildId(admin_guild_id)): FromState<AdminGuildId>,
+    Extension(ReceivedAt(received_at)): Extension<ReceivedAt>,
+) -> Result<(), TwilightError> {
+    let mut content = format!(
+        "You are {} (`{}`), using locale `{locale}`.",
+        user.name, user.id
+    );
+    if let Some(GuildId(guild_id)) = guild_id {
+        _ = write!(content, "\nYou are in the server `{guild_id}`");
+        match member.and_then(|Member(member)| member.nick) {
+            Some(nick) => _ = write!(content, " as {nick}."),
+            None => content.push('.'),
+        }
+        if guild_id == admin_guild_id {
+            content.push_str("\nThis is the admin server.");
+        }
+    }
+    _ = write!(
+        content,
+        "\nI am the application `{}`, answering after {:?}.",
+        context.state.app_id,
+        received_at.elapsed()
+    );
+
+    let mut response = InteractionResponseDataBuilder::new().content(content);
+    if options.ephemeral.unwrap_or(false) {
+        response = response.flags(MessageFlags::EPHEMERAL);
+    } else {
+        let dismiss = dismiss::Component { owner: user.id };
+        response = response.components([dismiss.action_row()]);
+    }
+    context.reply(response.build()).await?;
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use crate::commands::TwilightError;
+use crate::context::CommandContext;
+use crate::framework::{CommandHandler, ComponentModel, FromComponentDataError};
+use crate::retry;
+use std::future::IntoFuture;
+use tracing::instrument;
+use twilight_model::channel::message::MessageFlags;
+use twilight_model::channel::message::component::{ActionRow, Button, ButtonStyle};
+use twilight_model::http::interaction::InteractionResponseType;
+use twilight_model::id::Id;
+use twilight_model::id::marker::UserMarker;
+use twilight_util::builder::InteractionResponseDataBuilder;
+
+/// Button deleting the message it is attached to, usable only by its owner.
+#[derive(Copy, Clone, Debug)]
+pub struct Component {
+    pub owner: Id<UserMarker>,
+}
+
+pub type Error = TwilightError;
+
+impl Component {
+    pub fn action_row(self) -> twilight_model::channel::message::Component {
+        twilight_model::channel::message::Component::ActionRow(ActionRow {
+            components: vec![twilight_model::channel::message::Component::Button(
+                Button {
+                    custom_id: Some(self.custom_id()),
+                    disabled: false,
+                    emoji: None,
+                    label: Some("Dismiss".to_owned()),
+                    style: ButtonStyle::Secondary,
+                    url: None,
+                    sku_id: None,
+                },
+            )],
+        })
+    }
+}
+
+impl ComponentModel for Component {
+    const NAME: &'static str = "dismiss";
+
+    fn from_arguments(arguments: &str) -> Result<Self, FromComponentDataError> {
+        let owner = arguments.parse().map_err(|_| {
+            FromComponentDataError::InvalidArguments(Self::NAME, arguments.to_owned())
+        })?;
+        Ok(Component { owner })
+    }
+
+    fn arguments(&self) -> String {
+        self.owner.to_string()
+    }
+}
+
+impl CommandHandler for Component {
+    type Context = CommandContext;
+    type Response = ();
+    type Error = Error;
+
+    #[instrument(level = "info")]
+    async fn handle(self, context: Self::Context) -> Result<Self::Response, Self::Error> {
+        if context.interaction.author_id() != Some(self.owner) {
+            context
+                .reply(
+                    InteractionResponseDataBuilder::new()
+                        .content("Only the user this message is for can dismiss it.")
+                        .flags(MessageFlags::EPHEMERAL)
+                        .build(),
+                )
+                .await?;
+            return Ok(());
+        }
+
+        context
+            .respond(InteractionResponseType::DeferredUpdateMessage, None)
+            .await?;
+        let client = context.state.interaction_client();
+        context
+            .state
+            .retry_policy
+            .retry(retry::token_deadline(context.interaction.id), || {
+                client
+                    .delete_response(&context.interaction.token)
+                    .into_future()
+            })
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+use crate::context::CommandContext;
+use crate::framework::{CommandHandler, ComponentModel, FromComponentData, FromComponentDataError};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use tracing::instrument;
+use twilight_model::application::interaction::message_component::MessageComponentInteractionData;
+use twilight_model::channel::message::Component;
+use twilight_model::channel::message::component::{Button, ButtonStyle};
+
+pub mod dismiss;
+
+/// Enabled button with a custom id and label, for handlers collecting their own components.
+pub fn button(custom_id: &str, label: &str, style: ButtonStyle) -> Component {
+    Component::Button(Button {
+        custom_id: Some(custom_id.to_owned()),
+        disabled: false,
+        emoji: None,
+        label: Some(label.to_owned()),
+        style,
+        url: None,
+        sku_id: None,
+    })
+}
+
+macro_rules! components_collection {
+    (Create collection $collection_name:ident
+    with error type $error_name:ident
+    with visibility $vis:vis
+    with context $context:ty;
+    from components: {
+        $($component_name:ident
+        at $component_type:path;
+        with error type $component_error_type:path,)*
+    }) => {
+        #[derive(Debug)]
+        $vis enum $error_name {
+            $($component_name($component_error_type),
+            )*
+        }
+
+        impl Error for $error_name {}
+        impl Display for $error_name {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    $($error_name::$component_name(inner) => {
+                        write!(f, "Component {} had error: {inner}", stringify!($component_name))
+                    })*
+                }
+            }
+        }
+
+        #[derive(Debug)]
+        $vis enum $collection_name {
+            $($component_name($component_type),
+            )*
+        }
+
+        impl FromComponentData for $collection_name {
+            #[instrument(level = "trace")]
+            fn from_component_data(
+                data: Box<MessageComponentInteractionData>,
+            ) -> Result<Self, FromComponentDataError> {
+                let (name, arguments) = data.custom_id.split_once(':').unwrap_or((&data.custom_id, ""));
+                match name {
+                    $(<$component_type>::NAME => {
+                        Ok(
+                            $collection_name
+                                ::$component_name(<$component_type>::from_arguments(arguments)?)
+                        )
+                    })*
+                    _ => Err(FromComponentDataError::UnknownComponent(data)),
+                }
+            }
+        }
+
+        impl CommandHandler for $collection_name {
+            type Context = $context;
+            type Response = ();
+            type Error = $error_name;
+
+            #[instrument(level = "debug")]
+            async fn handle(
+                self,
+                context: Self::Context,
+            ) -> Result<Self::Response, Self::Error> {
+                match self {
+                    $($collection_name::$component_name(component) => component
+                        .handle(context)
+                        .await
+                        .map_err($error_name::$component_name),
+                    )*
+                }
+            }
+        }
+    };
+}
+components_collection! {
+    Create collection Components
+    with error type ComponentError
+    with visibility pub
+    with context CommandContext;
+    from components: {
+        Dismiss at dismiss::Component; with error type dismiss::Error,
+    }
+}
@@ -0,0 +1,384 @@
+use crate::cache::CachedResource;
+use crate::executor::ExecutorConfig;
+use crate::shards::{ShardLayout, ShardSelection};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use thiserror::Error;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ApplicationMarker, ChannelMarker, GuildMarker};
+
+#[derive(Clone, Eq, PartialEq, Debug, Error)]
+pub enum ShardLayoutError {
+    #[error("SHARD_START and SHARD_END have to be set together")]
+    IncompleteRange,
+    #[error("SHARD_START ({start}) has to be less than SHARD_END ({end})")]
+    EmptyRange { start: u32, end: u32 },
+    #[error("CLUSTER_INDEX and CLUSTER_COUNT have to be set together")]
+    IncompleteCluster,
+    #[error("CLUSTER_INDEX ({index}) has to be less than CLUSTER_COUNT ({count})")]
+    InvalidClusterIndex { index: u32, count: u32 },
+    #[error("A shard range and a cluster can not both be configured")]
+    RangeAndCluster,
+    #[error("SHARD_TOTAL has to be set when SHARD_START and SHARD_END are")]
+    RangeWithoutTotal,
+    #[error("The configured shards include none of the {total} shards")]
+    NoShards { total: u32 },
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Could not read .env file: {0}")]
+    DotEnv(#[from] dotenv::Error),
+    #[error("Invalid configuration: {0}")]
+    Env(#[from] envy::Error),
+    #[error("{0}")]
+    Layout(#[from] ShardLayoutError),
+    #[error("DATABASE_POOL_SIZE has to be at least 1")]
+    EmptyDatabasePool,
+    #[error("Changes to {} only apply after a restart", .0.join(", "))]
+    RestartRequired(Vec<&'static str>),
+}
+
+/// Configuration read from the environment.
+///
+/// Only the audit settings can be changed by reloading, the rest requires a restart.
+#[derive(Deserialize)]
+pub struct EnvConfig {
+    pub discord_token: String,
+    pub application_id: Id<ApplicationMarker>,
+    pub admin_guild_id: Id<GuildMarker>,
+    #[serde(default = "EnvConfig::default_reshard_interval_secs")]
+    pub reshard_interval_secs: u64,
+    /// Has to be unique per process when the shards are split between processes.
+    #[serde(default = "EnvConfig::default_session_file")]
+    pub session_file: PathBuf,
+    /// Total shard count across all processes, defaults to Discord's recommendation.
+    pub shard_total: Option<u32>,
+    /// First shard run by this process.
+    pub shard_start: Option<u32>,
+    /// Shard after the last shard run by this process.
+    pub shard_end: Option<u32>,
+    /// Index of this process if the shards are split evenly between processes.
+    pub cluster_index: Option<u32>,
+    pub cluster_count: Option<u32>,
+    /// Directory used to coordinate identifying between processes on the same host.
+    pub identify_queue_dir: Option<PathBuf>,
+    #[serde(default = "EnvConfig::default_command_concurrency")]
+    pub command_concurrency: usize,
+    #[serde(default = "EnvConfig::default_guild_command_concurrency")]
+    pub guild_command_concurrency: usize,
+    #[serde(default = "EnvConfig::default_admin_command_concurrency")]
+    pub admin_command_concurrency: usize,
+    #[serde(default = "EnvConfig::default_command_queue_limit")]
+    pub command_queue_limit: u64,
+    /// Channel commands used in the admin guild are reported to, applies on reload.
+    pub audit_channel_id: Option<Id<ChannelMarker>>,
+    /// JSON lines file commands used in the admin guild are appended to, applies on reload.
+    pub audit_file: Option<PathBuf>,
+    /// Database file the bot's data is stored in.
+    #[serde(default = "EnvConfig::default_database_file")]
+    pub database_file: PathBuf,
+    /// Connections to the database kept open, at least 1.
+    #[serde(default = "EnvConfig::default_database_pool_size")]
+    pub database_pool_size: u32,
+    /// Resources kept in the in-memory cache, nothing is cached if empty.
+    #[serde(default)]
+    pub cache_resources: Vec<CachedResource>,
+}
+
+impl EnvConfig {
+    fn default_reshard_interval_secs() -> u64 {
+        60 * 60
+    }
+
+    fn default_session_file() -> PathBuf {
+        PathBuf::from("sessions.json")
+    }
+
+    fn default_command_concurrency() -> usize {
+        64
+    }
+
+    fn default_guild_command_concurrency() -> usize {
+        4
+    }
+
+    fn default_admin_command_concurrency() -> usize {
+        4
+    }
+
+    fn default_command_queue_limit() -> u64 {
+        256
+    }
+
+    fn default_database_file() -> PathBuf {
+        PathBuf::from("tetra.db")
+    }
+
+    fn default_database_pool_size() -> u32 {
+        4
+    }
+
+    /// Reads the configuration again, with the current `.env` file taking precedence over the environment.
+    ///
+    /// Variables loaded from `.env` at startup are part of the process environment,
+    /// so changes to the file would not be seen otherwise.
+    pub fn reload() -> Result<Self, ConfigError> {
+        let mut variables: HashMap<_, _> = std::env::vars().collect();
+        #[allow(
+            deprecated,
+            reason = "the suggested replacement does not override variables that are already set"
+        )]
+        match dotenv::from_path_iter(".env") {
+            Ok(file) => {
+                for variable in file {
+                    let (key, value) = variable?;
+                    variables.insert(key, value);
+                }
+            }
+            Err(error) if error.not_found() => {}
+            Err(error) => return Err(error.into()),
+        }
+
+        let config: Self = envy::from_iter(variables)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks the values that can be invalid even though they have the right type.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.shard_layout()?;
+        if self.database_pool_size == 0 {
+            return Err(ConfigError::EmptyDatabasePool);
+        }
+        Ok(())
+    }
+
+    /// Variables that differ in `reloaded` but only apply after a restart.
+    pub fn restart_required(&self, reloaded: &EnvConfig) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        let mut check = |name, differs| {
+            if differs {
+                changed.push(name);
+            }
+        };
+        check(
+            "DISCORD_TOKEN",
+            self.discord_token != reloaded.discord_token,
+        );
+        check(
+            "APPLICATION_ID",
+            self.application_id != reloaded.application_id,
+        );
+        check(
+            "ADMIN_GUILD_ID",
+            self.admin_guild_id != reloaded.admin_guild_id,
+        );
+        check(
+            "RESHARD_INTERVAL_SECS",
+            self.reshard_interval_secs != reloaded.reshard_interval_secs,
+        );
+        check("SESSION_FILE", self.session_file != reloaded.session_file);
+        check("SHARD_TOTAL", self.shard_total != reloaded.shard_total);
+        check("SHARD_START", self.shard_start != reloaded.shard_start);
+        check("SHARD_END", self.shard_end != reloaded.shard_end);
+        check(
+            "CLUSTER_INDEX",
+            self.cluster_index != reloaded.cluster_index,
+        );
+        check(
+            "CLUSTER_COUNT",
+            self.cluster_count != reloaded.cluster_count,
+        );
+        check(
+            "IDENTIFY_QUEUE_DIR",
+            self.identify_queue_dir != reloaded.identify_queue_dir,
+        );
+        check(
+            "COMMAND_CONCURRENCY",
+            self.command_concurrency != reloaded.command_concurrency,
+        );
+        check(
+            "GUILD_COMMAND_CONCURRENCY",
+            self.guild_command_concurrency != reloaded.guild_command_concurrency,
+        );
+        check(
+            "ADMIN_COMMAND_CONCURRENCY",
+            self.admin_command_concurrency != reloaded.admin_command_concurrency,
+        );
+        check(
+            "COMMAND_QUEUE_LIMIT",
+            self.command_queue_limit != reloaded.command_queue_limit,
+        );
+        check(
+            "DATABASE_FILE",
+            self.database_file != reloaded.database_file,
+        );
+        check(
+            "DATABASE_POOL_SIZE",
+            self.database_pool_size != reloaded.database_pool_size,
+        );
+        check(
+            "CACHE_RESOURCES",
+            self.cache_resources != reloaded.cache_resources,
+        );
+        changed
+    }
+
+    pub fn executor_config(&self) -> ExecutorConfig {
+        ExecutorConfig {
+            global_limit: self.command_concurrency,
+            guild_limit: self.guild_command_concurrency,
+            admin_limit: self.admin_command_concurrency,
+            queue_limit: self.command_queue_limit,
+            admin_guild_id: self.admin_guild_id,
+        }
+    }
+
+    pub fn shard_layout(&self) -> Result<ShardLayout, ShardLayoutError> {
+        let range = match (self.shard_start, self.shard_end) {
+            (Some(start), Some(end)) if start < end => Some(ShardSelection::Range { start, end }),
+            (Some(start), Some(end)) => return Err(ShardLayoutError::EmptyRange { start, end }),
+            (None, None) => None,
+            _ => return Err(ShardLayoutError::IncompleteRange),
+        };
+        let cluster = match (self.cluster_index, self.cluster_count) {
+            (Some(index), Some(count)) if index < count => {
+                Some(ShardSelection::Cluster { index, count })
+            }
+            (Some(index), Some(count)) => {
+                return Err(ShardLayoutError::InvalidClusterIndex { index, count });
+            }
+            (None, None) => None,
+            _ => return Err(ShardLayoutError::IncompleteCluster),
+        };
+
+        let selection = match (range, cluster) {
+            (Some(_), Some(_)) => return Err(ShardLayoutError::RangeAndCluster),
+            // The range would silently shrink or become empty if the recommended total changed
+            (Some(_), None) if self.shard_total.is_none() => {
+                return Err(ShardLayoutError::RangeWithoutTotal);
+            }
+            (Some(selection), None) | (None, Some(selection)) => selection,
+            (None, None) => ShardSelection::All,
+        };
+
+        let layout = ShardLayout {
+            total: self.shard_total,
+            selection,
+        };
+        if let Some(total) = layout.total {
+            layout.non_empty_shards(total)?;
+        }
+        Ok(layout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(variables: &[(&str, &str)]) -> Result<ShardLayout, ShardLayoutError> {
+        let required = [
+            ("DISCORD_TOKEN", ""),
+            ("APPLICATION_ID", "1"),
+            ("ADMIN_GUILD_ID", "2"),
+        ];
+        let config: EnvConfig = envy::from_iter(
+            required
+                .iter()
+                .chain(variables)
+                .map(|&(key, value)| (key.to_owned(), value.to_owned())),
+        )
+        .unwrap();
+        config.shard_layout()
+    }
+
+    #[test]
+    fn all_shards() {
+        assert_eq!(
+            layout(&[]),
+            Ok(ShardLayout {
+                total: None,
+                selection: ShardSelection::All,
+            })
+        );
+    }
+
+    #[test]
+    fn range() {
+        assert_eq!(
+            layout(&[
+                ("SHARD_TOTAL", "8"),
+                ("SHARD_START", "2"),
+                ("SHARD_END", "4")
+            ]),
+            Ok(ShardLayout {
+                total: Some(8),
+                selection: ShardSelection::Range { start: 2, end: 4 },
+            })
+        );
+        assert_eq!(
+            layout(&[("SHARD_START", "2"), ("SHARD_END", "4")]),
+            Err(ShardLayoutError::RangeWithoutTotal)
+        );
+        assert_eq!(
+            layout(&[("SHARD_TOTAL", "8"), ("SHARD_START", "2")]),
+            Err(ShardLayoutError::IncompleteRange)
+        );
+        assert_eq!(
+            layout(&[
+                ("SHARD_TOTAL", "8"),
+                ("SHARD_START", "4"),
+                ("SHARD_END", "4")
+            ]),
+            Err(ShardLayoutError::EmptyRange { start: 4, end: 4 })
+        );
+        assert_eq!(
+            layout(&[
+                ("SHARD_TOTAL", "8"),
+                ("SHARD_START", "8"),
+                ("SHARD_END", "10")
+            ]),
+            Err(ShardLayoutError::NoShards { total: 8 })
+        );
+    }
+
+    #[test]
+    fn cluster() {
+        assert_eq!(
+            layout(&[("CLUSTER_INDEX", "1"), ("CLUSTER_COUNT", "3")]),
+            Ok(ShardLayout {
+                total: None,
+                selection: ShardSelection::Cluster { index: 1, count: 3 },
+            })
+        );
+        assert_eq!(
+            layout(&[("CLUSTER_INDEX", "3"), ("CLUSTER_COUNT", "3")]),
+            Err(ShardLayoutError::InvalidClusterIndex { index: 3, count: 3 })
+        );
+        assert_eq!(
+            layout(&[("CLUSTER_COUNT", "3")]),
+            Err(ShardLayoutError::IncompleteCluster)
+        );
+        // With 4 shards the clusters get 2, 2 and none
+        assert_eq!(
+            layout(&[
+                ("SHARD_TOTAL", "4"),
+                ("CLUSTER_INDEX", "2"),
+                ("CLUSTER_COUNT", "3"),
+            ]),
+            Err(ShardLayoutError::NoShards { total: 4 })
+        );
+        assert_eq!(
+            layout(&[
+                ("SHARD_START", "0"),
+                ("SHARD_END", "2"),
+                ("CLUSTER_INDEX", "0"),
+                ("CLUSTER_COUNT", "2"),
+            ]),
+            Err(ShardLayoutError::RangeAndCluster)
+        );
+    }
+}
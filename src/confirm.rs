@@ -0,0 +1,133 @@
+use crate::collectors::ComponentFilter;
+use crate::commands::TwilightError;
+use crate::components::button;
+use crate::context::{Acknowledged, CommandContext, GuildCommandContext};
+use crate::retry;
+use std::future::{Future, IntoFuture};
+use std::time::Duration;
+use tracing::{debug, instrument};
+use twilight_model::channel::message::component::{ActionRow, ButtonStyle};
+use twilight_model::channel::message::{Component, MessageFlags};
+use twilight_model::http::interaction::InteractionResponseType;
+use twilight_util::builder::InteractionResponseDataBuilder;
+
+/// Time the user has to answer before the command is cancelled.
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(30);
+const CONFIRM: &str = "confirm:yes";
+const CANCEL: &str = "confirm:no";
+
+/// Answers the button press by replacing the prompt with the outcome and removing its buttons.
+async fn answer_press(press: &CommandContext, outcome: &str) -> Result<(), TwilightError> {
+    press
+        .respond(
+            InteractionResponseType::UpdateMessage,
+            Some(
+                InteractionResponseDataBuilder::new()
+                    .content(outcome)
+                    .components([])
+                    .build(),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Replaces the prompt with the outcome and removes its buttons, when there is no press to answer.
+async fn close_prompt(context: &CommandContext, outcome: &str) -> Result<(), TwilightError> {
+    let client = context.state.interaction_client();
+    context
+        .state
+        .retry_policy
+        .retry(retry::token_deadline(context.interaction.id), || {
+            client
+                .update_response(&context.interaction.token)
+                .content(Some(outcome))
+                .components(Some(&[]))
+                .into_future()
+        })
+        .await?;
+    Ok(())
+}
+
+/// Asks the user to confirm with an ephemeral prompt.
+///
+/// Returns the context of the confirming button press, which is already answered so the command
+/// replies with followups, or `None` if the user cancelled or did not answer in time.
+#[instrument(level = "debug", skip(context))]
+pub async fn confirm(
+    context: &CommandContext,
+    prompt: &str,
+) -> Result<Option<CommandContext>, TwilightError> {
+    context
+        .reply(
+            InteractionResponseDataBuilder::new()
+                .content(prompt)
+                .flags(MessageFlags::EPHEMERAL)
+                .components([Component::ActionRow(ActionRow {
+                    components: vec![
+                        button(CONFIRM, "Confirm", ButtonStyle::Danger),
+                        button(CANCEL, "Cancel", ButtonStyle::Secondary),
+                    ],
+                })])
+                .build(),
+        )
+        .await?;
+    let message = context.response_message().await?;
+    let mut filter = ComponentFilter::new()
+        .message(message.id)
+        .custom_id(CONFIRM)
+        .custom_id(CANCEL);
+    if let Some(user_id) = context.interaction.author_id() {
+        filter = filter.user(user_id);
+    }
+
+    match context.next_component(filter, CONFIRM_TIMEOUT).await {
+        // The press has to be answered within its response deadline, before the command runs
+        Some(mut press) if press.custom_id() == Some(CONFIRM) => {
+            answer_press(&press, "Confirmed.").await?;
+            press.extensions.insert(Acknowledged);
+            Ok(Some(press))
+        }
+        Some(press) => {
+            answer_press(&press, "Cancelled.").await?;
+            Ok(None)
+        }
+        None => {
+            debug!("Confirmation timed out");
+            close_prompt(context, "No answer, cancelled.").await?;
+            Ok(None)
+        }
+    }
+}
+
+/// Contexts of commands that can ask for confirmation before they are handled.
+pub trait Confirmable: Sized {
+    fn command_context(&self) -> &CommandContext;
+
+    /// Returns the context to handle the command with, or `None` if it was not confirmed.
+    fn confirm(
+        self,
+        prompt: &'static str,
+    ) -> impl Future<Output = Result<Option<Self>, TwilightError>> + Send;
+}
+
+impl Confirmable for CommandContext {
+    fn command_context(&self) -> &CommandContext {
+        self
+    }
+
+    async fn confirm(self, prompt: &'static str) -> Result<Option<Self>, TwilightError> {
+        confirm(&self, prompt).await
+    }
+}
+
+impl Confirmable for GuildCommandContext {
+    fn command_context(&self) -> &CommandContext {
+        &self.context
+    }
+
+    async fn confirm(self, prompt: &'static str) -> Result<Option<Self>, TwilightError> {
+        let confirmed = confirm(&self.context, prompt).await?;
+        Ok(confirmed.map(|context| GuildCommandContext { context, ..self }))
+    }
+}
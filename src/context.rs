@@ -1,27 +1,67 @@
-use crate::framework::CommandContextFactory;
+use crate::blocklist::Blocklist;
+use crate::cache::Cache;
+use crate::collectors::{Collector, Collectors, ComponentFilter};
+use crate::commands::TwilightError;
+use crate::config::{ConfigError, EnvConfig};
+use crate::executor::{Capacity, CommandExecutor};
+use crate::extensions::Extensions;
+use crate::extract::{Rejection, reply_rejection};
+use crate::framework::{CommandContextFactory, EventContextFactory, HandlerContext};
+use crate::hooks::GuildCooldowns;
+use crate::metrics::Metrics;
+use crate::retry::{self, RetryPolicy};
+use crate::scheduler::Scheduler;
+use crate::settings::SettingsCache;
+use crate::storage::Storage;
 use crate::util::OmitDebug;
+use std::any::type_name;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
 use std::fmt::{Debug, Formatter};
-use std::sync::Arc;
+use std::future::IntoFuture;
+use std::ops::Deref;
 use std::sync::atomic::{AtomicBool, Ordering};
-use tracing::{info, instrument};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{info, instrument, warn};
 use twilight_gateway::MessageSender;
 use twilight_gateway::error::ChannelError;
 use twilight_http::client::InteractionClient;
-use twilight_http::response::marker::EmptyBody;
 use twilight_http::{Client, Response};
-use twilight_model::application::interaction::Interaction;
+use twilight_model::application::command::CommandOptionChoice;
+use twilight_model::application::interaction::{
+    Interaction, InteractionContextType, InteractionData,
+};
+use twilight_model::channel::message::AllowedMentions;
+use twilight_model::channel::{Channel, Message};
 use twilight_model::gateway::CloseFrame;
+use twilight_model::guild::PartialMember;
 use twilight_model::http::interaction::{
     InteractionResponse, InteractionResponseData, InteractionResponseType,
 };
 use twilight_model::id::Id;
-use twilight_model::id::marker::ApplicationMarker;
+use twilight_model::id::marker::{ApplicationMarker, GuildMarker};
+use twilight_util::builder::InteractionResponseDataBuilder;
 
 pub struct State {
     pub client: Client,
-    pub senders: Vec<MessageSender>,
+    /// Senders of the currently active shards, by shard number.
+    pub senders: RwLock<BTreeMap<u32, MessageSender>>,
     pub app_id: Id<ApplicationMarker>,
     pub shutdown: AtomicBool,
+    /// Whether the process should exit with [`RESTART_EXIT_CODE`] after shutting down.
+    pub restart: AtomicBool,
+    /// Configuration, replaced when it is reloaded.
+    pub config: RwLock<Arc<EnvConfig>>,
+    pub executor: CommandExecutor,
+    pub metrics: Arc<Metrics>,
+    pub retry_policy: RetryPolicy,
+    pub collectors: Collectors,
+    /// Whether interactions outside the admin guild are rejected.
+    pub maintenance: AtomicBool,
+    pub blocklist: Blocklist,
+    /// Application defined shared state, populated at startup.
+    pub extensions: Extensions,
 }
 
 impl Debug for State {
@@ -31,15 +71,94 @@ impl Debug for State {
             .field("senders", &OmitDebug)
             .field("app_id", &self.app_id)
             .field("shutdown", &self.shutdown)
+            .field("restart", &self.restart)
+            .field("config", &OmitDebug)
+            .field("executor", &self.executor)
+            .field("metrics", &self.metrics)
+            .field("retry_policy", &self.retry_policy)
+            .field("collectors", &self.collectors)
+            .field("maintenance", &self.maintenance)
+            .field("blocklist", &self.blocklist)
+            .field("extensions", &self.extensions)
             .finish()
     }
 }
 
+/// Exit code telling the process supervisor to start the bot again.
+pub const RESTART_EXIT_CODE: u8 = 75;
+
 impl State {
     pub fn interaction_client(&self) -> InteractionClient<'_> {
         self.client.interaction(self.app_id)
     }
 
+    /// A value registered in [`State::extensions`] at startup.
+    ///
+    /// # Panics
+    ///
+    /// If no value of the type was registered, which is a bug in the startup code.
+    fn registered<T>(&self) -> &T
+    where
+        T: Send + Sync + 'static,
+    {
+        self.extensions
+            .get::<T>()
+            .unwrap_or_else(|| panic!("{} is not registered in the state", type_name::<T>()))
+    }
+
+    /// The database.
+    pub fn storage(&self) -> &Storage {
+        self.registered()
+    }
+
+    pub fn guild_settings(&self) -> &SettingsCache {
+        self.registered()
+    }
+
+    pub fn cooldowns(&self) -> &GuildCooldowns {
+        self.registered()
+    }
+
+    pub fn scheduler(&self) -> &Scheduler {
+        self.registered()
+    }
+
+    /// Gateway state of the configured resources, if any are cached.
+    pub fn cache(&self) -> Option<&Cache> {
+        self.extensions.get()
+    }
+
+    /// The current configuration.
+    pub fn config(&self) -> Arc<EnvConfig> {
+        self.config
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Reads the configuration again and replaces the current one.
+    ///
+    /// The current configuration stays in place if the new one is invalid or changes variables
+    /// that only apply after a restart, see [`EnvConfig`].
+    #[instrument(level = "info", skip(self))]
+    pub fn reload_config(&self) -> Result<(), ConfigError> {
+        let config = EnvConfig::reload()?;
+        let restart_required = self.config().restart_required(&config);
+        if !restart_required.is_empty() {
+            return Err(ConfigError::RestartRequired(restart_required));
+        }
+        *self.config.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(config);
+        info!("Reloaded config");
+        Ok(())
+    }
+
+    /// Shuts down like [`State::send_shutdown`], but asks the process supervisor to restart the bot.
+    #[instrument]
+    pub fn send_restart(&self) -> Result<(), Vec<ChannelError>> {
+        self.restart.store(true, Ordering::Release);
+        self.send_shutdown()
+    }
+
     #[instrument]
     pub fn send_shutdown(&self) -> Result<(), Vec<ChannelError>> {
         // Shutdown method should be idempotent
@@ -50,8 +169,11 @@ impl State {
 
         let close_errors: Vec<_> = self
             .senders
-            .iter()
-            .map(|sender| sender.close(CloseFrame::NORMAL))
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            // Resume close code keeps the sessions valid, so they can be resumed after a restart
+            .map(|sender| sender.close(CloseFrame::RESUME))
             .filter_map(Result::err)
             .collect();
 
@@ -63,50 +185,420 @@ impl State {
     }
 }
 
+/// Guild the admin commands are registered in, stored in the [`State`] extensions.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct AdminGuildId(pub Id<GuildMarker>);
+
+/// Time the interaction was received from the gateway, stored in the per request extensions.
+#[derive(Copy, Clone, Debug)]
+pub struct ReceivedAt(pub Instant);
+
+/// Marks an interaction that was already responded to, stored in the per request extensions.
+///
+/// [`CommandContext::reply`] sends followups for such interactions.
+#[derive(Copy, Clone, Debug)]
+pub struct Acknowledged;
+
 #[derive(Clone, Debug)]
 pub struct ContextFactory {
     pub state: Arc<State>,
+    /// Per request values, which middleware can insert before the context is created.
+    pub extensions: Extensions,
 }
 
 impl ContextFactory {
     pub fn new(state: Arc<State>) -> Self {
-        ContextFactory { state }
+        ContextFactory {
+            state,
+            extensions: Extensions::new(),
+        }
     }
 }
 
-impl CommandContextFactory for ContextFactory {
-    type CommandContext = CommandContext;
+impl CommandContextFactory<CommandContext> for ContextFactory {
+    type Rejection = Infallible;
 
     #[instrument(level = "trace")]
-    fn create_context(self, interaction: Interaction) -> Self::CommandContext {
-        CommandContext {
+    async fn create_context(self, interaction: Interaction) -> Result<CommandContext, Infallible> {
+        Ok(CommandContext {
             state: self.state,
             interaction,
+            extensions: self.extensions,
+        })
+    }
+}
+
+impl HandlerContext for CommandContext {
+    const CONTEXTS: Option<&'static [InteractionContextType]> = None;
+}
+
+impl HandlerContext for GuildCommandContext {
+    const CONTEXTS: Option<&'static [InteractionContextType]> =
+        Some(&[InteractionContextType::Guild]);
+}
+
+impl CommandContextFactory<GuildCommandContext> for ContextFactory {
+    type Rejection = Rejection;
+
+    /// Rejects interactions from outside guilds, replying with the reason.
+    #[instrument(level = "trace")]
+    async fn create_context(
+        self,
+        interaction: Interaction,
+    ) -> Result<GuildCommandContext, Rejection> {
+        let Ok(context) =
+            CommandContextFactory::<CommandContext>::create_context(self, interaction).await;
+        match GuildCommandContext::new(context) {
+            Ok(context) => Ok(context),
+            Err((context, rejection)) => {
+                reply_rejection(&context, &rejection).await;
+                Err(rejection)
+            }
         }
     }
 }
 
+impl EventContextFactory for ContextFactory {
+    type EventContext = EventContext;
+
+    #[instrument(level = "trace")]
+    fn create_event_context(self) -> Self::EventContext {
+        EventContext { state: self.state }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EventContext {
+    pub state: Arc<State>,
+}
+
 #[derive(Clone, Debug)]
 pub struct CommandContext {
     pub state: Arc<State>,
     pub interaction: Interaction,
+    /// Per request values inserted by middleware.
+    pub extensions: Extensions,
+}
+
+/// Context of a command that can only be used in guilds.
+#[derive(Clone, Debug)]
+pub struct GuildCommandContext {
+    pub context: CommandContext,
+    pub guild_id: Id<GuildMarker>,
+    pub member: PartialMember,
+    pub channel: Channel,
+}
+
+impl GuildCommandContext {
+    #[allow(
+        clippy::result_large_err,
+        reason = "the context is handed back to reply"
+    )]
+    fn new(context: CommandContext) -> Result<Self, (CommandContext, Rejection)> {
+        let interaction = &context.interaction;
+        let guild_id = interaction.guild_id.ok_or(Rejection::NotInGuild);
+        let member = interaction.member.clone().ok_or(Rejection::NoMember);
+        let channel = interaction.channel.clone().ok_or(Rejection::NoChannel);
+        match (guild_id, member, channel) {
+            (Ok(guild_id), Ok(member), Ok(channel)) => Ok(GuildCommandContext {
+                context,
+                guild_id,
+                member,
+                channel,
+            }),
+            (Err(rejection), _, _) | (_, Err(rejection), _) | (_, _, Err(rejection)) => {
+                Err((context, rejection))
+            }
+        }
+    }
+}
+
+impl Deref for GuildCommandContext {
+    type Target = CommandContext;
+
+    fn deref(&self) -> &Self::Target {
+        &self.context
+    }
 }
 
 impl CommandContext {
+    /// Whether the command was invoked in the admin guild.
+    pub fn is_admin_guild(&self) -> bool {
+        let admin_guild = self.state.extensions.get::<AdminGuildId>();
+        admin_guild.is_some_and(|AdminGuildId(admin_guild)| {
+            self.interaction.guild_id == Some(*admin_guild)
+        })
+    }
+
+    /// The in-memory cache, if caching is configured.
+    pub fn cache(&self) -> Option<&Cache> {
+        self.state.cache()
+    }
+
+    /// Posts the content to the log channel of the guild, if it configured one.
+    ///
+    /// Failures are only logged, as the command already did what it logs.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn log_to_guild(&self, guild_id: Id<GuildMarker>, content: &str) {
+        let channel_id = match self.state.guild_settings().get(guild_id).await {
+            Ok(settings) => settings.log_channel_id,
+            Err(error) => {
+                warn!(%error, "Could not load guild settings, not logging");
+                return;
+            }
+        };
+        let Some(channel_id) = channel_id else {
+            return;
+        };
+
+        let allowed_mentions = AllowedMentions::default();
+        let result = self
+            .state
+            .retry_policy
+            .retry(retry::token_deadline(self.interaction.id), || {
+                self.state
+                    .client
+                    .create_message(channel_id)
+                    .content(content)
+                    .allowed_mentions(Some(&allowed_mentions))
+                    .into_future()
+            })
+            .await;
+        if let Err(error) = result {
+            warn!(%error, %channel_id, "Could not post to the guild's log channel");
+        }
+    }
+
+    /// Responds to the interaction, retrying transient failures until the response deadline.
+    pub async fn respond(
+        &self,
+        kind: InteractionResponseType,
+        data: Option<InteractionResponseData>,
+    ) -> Result<(), twilight_http::Error> {
+        let client = self.state.interaction_client();
+        let response = InteractionResponse { kind, data };
+        self.state
+            .retry_policy
+            .respond(retry::response_deadline(self.interaction.id), || {
+                client
+                    .create_response(self.interaction.id, &self.interaction.token, &response)
+                    .into_future()
+            })
+            .await
+    }
+
+    /// Responds with a message, or sends it as followup if the interaction was [`Acknowledged`].
     pub async fn reply(
         &self,
         response: InteractionResponseData,
-    ) -> Result<Response<EmptyBody>, twilight_http::Error> {
-        self.state
-            .interaction_client()
-            .create_response(
-                self.interaction.id,
-                &self.interaction.token,
-                &InteractionResponse {
-                    kind: InteractionResponseType::ChannelMessageWithSource,
-                    data: Some(response),
-                },
+    ) -> Result<(), twilight_http::Error> {
+        if self.extensions.get::<Acknowledged>().is_some() {
+            self.followup(&response).await?;
+        } else {
+            self.respond(
+                InteractionResponseType::ChannelMessageWithSource,
+                Some(response),
             )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Sends a followup message, retrying transient failures until the token expires.
+    pub async fn followup(
+        &self,
+        response: &InteractionResponseData,
+    ) -> Result<Response<Message>, twilight_http::Error> {
+        let client = self.state.interaction_client();
+        let components = response.components.as_deref().unwrap_or_default();
+        let embeds = response.embeds.as_deref().unwrap_or_default();
+        self.state
+            .retry_policy
+            .retry(retry::token_deadline(self.interaction.id), || {
+                let mut followup = client
+                    .create_followup(&self.interaction.token)
+                    .components(components)
+                    .embeds(embeds);
+                if let Some(content) = &response.content {
+                    followup = followup.content(content);
+                }
+                if let Some(flags) = response.flags {
+                    followup = followup.flags(flags);
+                }
+                followup.into_future()
+            })
             .await
     }
+
+    /// The message sent as response to the interaction.
+    pub async fn response_message(&self) -> Result<Message, TwilightError> {
+        let client = self.state.interaction_client();
+        let response = self
+            .state
+            .retry_policy
+            .retry(retry::token_deadline(self.interaction.id), || {
+                client.response(&self.interaction.token).into_future()
+            })
+            .await?;
+        Ok(response.model().await?)
+    }
+
+    /// Custom id of the component, if this is a component interaction.
+    pub fn custom_id(&self) -> Option<&str> {
+        match &self.interaction.data {
+            Some(InteractionData::MessageComponent(data)) => Some(&data.custom_id),
+            _ => None,
+        }
+    }
+
+    /// Starts collecting component interactions matching the filter.
+    ///
+    /// Start collecting before sending the components, so fast presses are not missed.
+    pub fn collect(&self, filter: ComponentFilter) -> ComponentCollector<'_> {
+        ComponentCollector {
+            context: self,
+            collector: self.state.collectors.collect(filter),
+        }
+    }
+
+    /// Waits for the next component interaction matching the filter, returning a context to respond to it.
+    pub async fn next_component(
+        &self,
+        filter: ComponentFilter,
+        timeout: Duration,
+    ) -> Option<CommandContext> {
+        self.collect(filter).next(timeout).await
+    }
+
+    /// Responds to an autocomplete interaction with the choices.
+    pub async fn autocomplete(
+        &self,
+        choices: impl IntoIterator<Item = CommandOptionChoice>,
+    ) -> Result<(), twilight_http::Error> {
+        self.respond(
+            InteractionResponseType::ApplicationCommandAutocompleteResult,
+            Some(
+                InteractionResponseDataBuilder::new()
+                    .choices(choices)
+                    .build(),
+            ),
+        )
+        .await
+    }
+}
+
+/// Component interactions collected for a command.
+#[derive(Debug)]
+pub struct ComponentCollector<'a> {
+    context: &'a CommandContext,
+    collector: Collector<'a>,
+}
+
+impl ComponentCollector<'_> {
+    /// Waits for the next matching component interaction, returning a context to respond to it.
+    ///
+    /// The command gives up its executor capacity while waiting, so it does not block other commands.
+    /// The returned context keeps the command's extensions.
+    pub async fn next(&mut self, timeout: Duration) -> Option<CommandContext> {
+        let next = self.collector.next(timeout);
+        let interaction = match self.context.extensions.get::<Capacity>() {
+            Some(capacity) => capacity.release_while(next).await,
+            None => next.await,
+        }?;
+        Some(CommandContext {
+            state: self.context.state.clone(),
+            interaction,
+            extensions: self.context.extensions.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocklist::Blocklist;
+    use crate::storage::Storage;
+    use std::path::Path;
+
+    async fn state() -> Arc<State> {
+        let config: EnvConfig = envy::from_iter([
+            ("DISCORD_TOKEN".to_owned(), String::new()),
+            ("APPLICATION_ID".to_owned(), "1".to_owned()),
+            ("ADMIN_GUILD_ID".to_owned(), "2".to_owned()),
+        ])
+        .unwrap();
+        let storage = Storage::open(Path::new(":memory:"), 1).unwrap();
+        let metrics = Arc::new(Metrics::default());
+        Arc::new(State {
+            client: Client::new(String::new()),
+            senders: RwLock::new(BTreeMap::new()),
+            app_id: config.application_id,
+            shutdown: AtomicBool::new(false),
+            restart: AtomicBool::new(false),
+            executor: CommandExecutor::new(config.executor_config(), metrics.clone()),
+            config: RwLock::new(Arc::new(config)),
+            metrics,
+            retry_policy: RetryPolicy::default(),
+            collectors: Collectors::default(),
+            maintenance: AtomicBool::new(false),
+            blocklist: Blocklist::load(storage.clone()).await.unwrap(),
+            extensions: Extensions::new().with(storage),
+        })
+    }
+
+    fn button_press(custom_id: &str) -> Interaction {
+        serde_json::from_value(serde_json::json!({
+            "id": "10",
+            "application_id": "1",
+            "type": 3,
+            "token": "token",
+            "authorizing_integration_owners": {},
+            "entitlements": [],
+            "user": {"id": "3", "username": "user", "discriminator": "0", "avatar": null},
+            "message": {
+                "id": "4",
+                "channel_id": "5",
+                "author": {"id": "1", "username": "bot", "discriminator": "0", "avatar": null},
+                "content": "",
+                "timestamp": "2026-01-01T00:00:00+00:00",
+                "edited_timestamp": null,
+                "tts": false,
+                "mention_everyone": false,
+                "mentions": [],
+                "mention_roles": [],
+                "attachments": [],
+                "embeds": [],
+                "pinned": false,
+                "type": 0
+            },
+            "data": {"custom_id": custom_id, "component_type": 2},
+            "version": 1
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn collected_context_keeps_extensions() {
+        let state = state().await;
+        let received_at = Instant::now();
+        let context = CommandContext {
+            state: state.clone(),
+            interaction: button_press("command"),
+            extensions: Extensions::new().with(ReceivedAt(received_at)),
+        };
+
+        let mut collector = context.collect(ComponentFilter::new().custom_id("confirm"));
+        assert!(state.collectors.deliver(button_press("confirm")).is_none());
+        let mut press = collector.next(Duration::from_secs(1)).await.unwrap();
+        press.extensions.insert(Acknowledged);
+
+        assert_eq!(press.custom_id(), Some("confirm"));
+        assert!(
+            press
+                .extensions
+                .get::<ReceivedAt>()
+                .is_some_and(|&ReceivedAt(instant)| instant == received_at)
+        );
+        assert!(context.extensions.get::<Acknowledged>().is_none());
+    }
 }
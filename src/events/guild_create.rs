@@ -0,0 +1,25 @@
+use crate::context::EventContext;
+use crate::framework::EventHandler;
+use std::convert::Infallible;
+use tracing::{debug, instrument};
+use twilight_model::gateway::payload::incoming::GuildCreate;
+
+#[derive(Debug)]
+pub struct Handler;
+
+pub type Error = Infallible;
+
+impl EventHandler for Handler {
+    type Event = GuildCreate;
+    type Context = EventContext;
+    type Error = Error;
+
+    #[instrument(level = "debug", skip_all, fields(guild.id = %event.id()))]
+    async fn handle(event: Self::Event, _context: Self::Context) -> Result<(), Self::Error> {
+        match event {
+            GuildCreate::Available(guild) => debug!(guild.name = %guild.name, "Guild available"),
+            GuildCreate::Unavailable(_) => debug!("Guild unavailable"),
+        }
+        Ok(())
+    }
+}
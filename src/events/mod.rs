@@ -0,0 +1,93 @@
+use crate::context::EventContext;
+use crate::framework::{EventContextFactory, EventRunner};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use tracing::instrument;
+use twilight_gateway::EventTypeFlags;
+use twilight_model::gateway::Intents;
+use twilight_model::gateway::event::Event;
+
+mod guild_create;
+mod ready;
+
+macro_rules! events_collection {
+    (Create collection $collection_name:ident
+    with error type $error_name:ident
+    with visibility $vis:vis
+    with context $context:ty;
+    from handlers: {
+        $($handler_name:ident
+        at $handler_type:path;
+        with error type $handler_error_type:path,)*
+    }) => {
+        #[derive(Debug)]
+        $vis enum $error_name {
+            $($handler_name($handler_error_type),
+            )*
+        }
+
+        impl Error for $error_name {}
+        impl Display for $error_name {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    $($error_name::$handler_name(inner) => {
+                        write!(f, "Event handler {} had error: {inner}", stringify!($handler_name))
+                    })*
+                }
+            }
+        }
+
+        #[derive(Debug)]
+        $vis struct $collection_name;
+
+        impl<TContextFactory> EventRunner<TContextFactory> for $collection_name
+        where
+            TContextFactory: EventContextFactory<EventContext = $context> + Clone + Send + 'static,
+        {
+            type Error = Vec<$error_name>;
+
+            const EVENT_TYPES: EventTypeFlags = EventTypeFlags::empty()
+                $(.union(<$handler_type as EventRunner<TContextFactory>>::EVENT_TYPES))*;
+            const INTENTS: Intents = Intents::empty()
+                $(.union(<$handler_type as EventRunner<TContextFactory>>::INTENTS))*;
+
+            #[instrument(level = "debug", skip_all, fields(event.kind = ?event.kind()))]
+            async fn run(
+                context_factory: TContextFactory,
+                event: Event,
+            ) -> Result<(), Self::Error> {
+                let event_type = EventTypeFlags::from(event.kind());
+                let mut errors = Vec::new();
+
+                $(if <$handler_type as EventRunner<TContextFactory>>::EVENT_TYPES
+                    .intersects(event_type)
+                {
+                    if let Err(error) = <$handler_type as EventRunner<TContextFactory>>::run(
+                        context_factory.clone(),
+                        event.clone(),
+                    )
+                    .await
+                    {
+                        errors.push($error_name::$handler_name(error));
+                    }
+                })*
+
+                if errors.is_empty() {
+                    Ok(())
+                } else {
+                    Err(errors)
+                }
+            }
+        }
+    };
+}
+events_collection! {
+    Create collection Events
+    with error type EventError
+    with visibility pub
+    with context EventContext;
+    from handlers: {
+        GuildCreate at guild_create::Handler; with error type guild_create::Error,
+        Ready at ready::Handler; with error type ready::Error,
+    }
+}
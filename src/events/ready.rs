@@ -0,0 +1,34 @@
+use crate::context::EventContext;
+use crate::framework::EventHandler;
+use std::convert::Infallible;
+use tracing::{info, instrument, warn};
+use twilight_model::gateway::payload::incoming::Ready;
+
+#[derive(Debug)]
+pub struct Handler;
+
+pub type Error = Infallible;
+
+impl EventHandler for Handler {
+    type Event = Ready;
+    type Context = EventContext;
+    type Error = Error;
+
+    #[instrument(level = "info", skip_all, fields(shard = ?event.shard))]
+    async fn handle(event: Self::Event, context: Self::Context) -> Result<(), Self::Error> {
+        if event.application.id != context.state.app_id {
+            warn!(
+                gateway_app_id = %event.application.id,
+                configured_app_id = %context.state.app_id,
+                "Gateway application ID does not match configured application ID",
+            );
+        }
+
+        info!(
+            user = %event.user.name,
+            guilds = event.guilds.len(),
+            "Shard ready",
+        );
+        Ok(())
+    }
+}
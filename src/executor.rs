@@ -0,0 +1,227 @@
+use crate::metrics::Metrics;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::task::JoinHandle;
+use tracing::{Instrument, debug, instrument, trace_span, warn};
+use twilight_model::id::Id;
+use twilight_model::id::marker::GuildMarker;
+
+/// Interval the executor is checked at while draining.
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Copy, Clone, Debug)]
+pub struct ExecutorConfig {
+    /// Commands executing at the same time, outside the admin guild.
+    pub global_limit: usize,
+    /// Commands executing at the same time per guild.
+    pub guild_limit: usize,
+    /// Commands executing at the same time in the admin guild, independent of the global limit.
+    pub admin_limit: usize,
+    /// Commands waiting for capacity before new commands are rejected.
+    pub queue_limit: u64,
+    pub admin_guild_id: Id<GuildMarker>,
+}
+
+/// Bounded execution of commands with global and per guild concurrency limits.
+///
+/// Commands from the admin guild use a separate lane, so they can not be starved by other guilds.
+#[derive(Debug)]
+pub struct CommandExecutor {
+    config: ExecutorConfig,
+    global: Arc<Semaphore>,
+    admin: Arc<Semaphore>,
+    guilds: Mutex<HashMap<Id<GuildMarker>, Arc<Semaphore>>>,
+    metrics: Arc<Metrics>,
+}
+
+impl CommandExecutor {
+    pub fn new(config: ExecutorConfig, metrics: Arc<Metrics>) -> Self {
+        CommandExecutor {
+            global: Arc::new(Semaphore::new(config.global_limit)),
+            admin: Arc::new(Semaphore::new(config.admin_limit)),
+            guilds: Mutex::new(HashMap::new()),
+            config,
+            metrics,
+        }
+    }
+
+    /// Reserves a place in the queue, or returns `None` if the queue is full.
+    #[instrument(level = "trace", skip(self))]
+    pub fn try_reserve(&self, guild_id: Option<Id<GuildMarker>>) -> Option<Reservation> {
+        if guild_id == Some(self.config.admin_guild_id) {
+            Metrics::increment(&self.metrics.admin_queue_depth);
+            return Some(Reservation {
+                capacity: Capacity::new(self.admin.clone(), None),
+                is_admin: true,
+                metrics: self.metrics.clone(),
+            });
+        }
+
+        if Metrics::increment(&self.metrics.queue_depth) >= self.config.queue_limit {
+            Metrics::decrement(&self.metrics.queue_depth);
+            Metrics::increment(&self.metrics.rejected_commands);
+            debug!("Command queue full, rejecting command");
+            return None;
+        }
+
+        Some(Reservation {
+            capacity: Capacity::new(
+                self.global.clone(),
+                guild_id.map(|guild_id| self.guild_semaphore(guild_id)),
+            ),
+            is_admin: false,
+            metrics: self.metrics.clone(),
+        })
+    }
+
+    /// Waits until no commands are queued or running, returning whether that happened before the timeout.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn drain(&self, timeout: Duration) -> bool {
+        let idle = async {
+            while self.metrics.queue_depth.load(Ordering::Relaxed) > 0
+                || self.metrics.admin_queue_depth.load(Ordering::Relaxed) > 0
+                || self.metrics.running_commands.load(Ordering::Relaxed) > 0
+            {
+                tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+            }
+        };
+        let drained = tokio::time::timeout(timeout, idle).await.is_ok();
+        if !drained {
+            warn!(metrics = ?self.metrics.snapshot(), "Commands still running after drain timeout");
+        }
+        drained
+    }
+
+    fn guild_semaphore(&self, guild_id: Id<GuildMarker>) -> Arc<Semaphore> {
+        let mut guilds = self.guilds.lock().unwrap_or_else(PoisonError::into_inner);
+        // Semaphores nobody is waiting on or holding permits of can be recreated when needed
+        guilds.retain(|_, semaphore| Arc::strong_count(semaphore) > 1);
+        guilds
+            .entry(guild_id)
+            .or_insert_with(|| Arc::new(Semaphore::new(self.config.guild_limit)))
+            .clone()
+    }
+}
+
+/// Permits of the guild and the lane a command executes in.
+type Permits = (Option<OwnedSemaphorePermit>, Option<OwnedSemaphorePermit>);
+
+/// Capacity a command executes with, stored in the per request extensions.
+#[derive(Clone, Debug)]
+pub struct Capacity {
+    lane: Arc<Semaphore>,
+    guild: Option<Arc<Semaphore>>,
+    held: Arc<Mutex<Option<Permits>>>,
+}
+
+impl Capacity {
+    fn new(lane: Arc<Semaphore>, guild: Option<Arc<Semaphore>>) -> Self {
+        Capacity {
+            lane,
+            guild,
+            held: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    async fn acquire(&self) {
+        // Semaphores are never closed
+        let guild = match &self.guild {
+            Some(guild) => guild.clone().acquire_owned().await.ok(),
+            None => None,
+        };
+        let lane = self.lane.clone().acquire_owned().await.ok();
+        *self.held.lock().unwrap_or_else(PoisonError::into_inner) = Some((guild, lane));
+    }
+
+    fn release(&self) {
+        drop(
+            self.held
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take(),
+        );
+    }
+
+    /// Gives up the capacity while waiting for the future, e.g. for a user to press a button,
+    /// then waits for capacity again before continuing.
+    pub async fn release_while<F: Future>(&self, future: F) -> F::Output {
+        self.release();
+        let output = future.await;
+        self.acquire().await;
+        output
+    }
+}
+
+/// A place in the command queue, released when the command starts executing.
+#[derive(Debug)]
+pub struct Reservation {
+    capacity: Capacity,
+    /// Whether the reservation is in the admin lane, which is not counted against the queue limit.
+    is_admin: bool,
+    metrics: Arc<Metrics>,
+}
+
+impl Reservation {
+    /// Capacity the command will execute with.
+    pub fn capacity(&self) -> Capacity {
+        self.capacity.clone()
+    }
+
+    /// Spawns the command, which runs as soon as there is capacity for it.
+    pub fn spawn<F>(self, command: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        tokio::spawn(
+            async move {
+                let capacity = Held(self.capacity.clone());
+                capacity.0.acquire().await;
+
+                let _running = Running::new(self.metrics.clone());
+                drop(self);
+                command.await
+            }
+            .instrument(trace_span!("queued command")),
+        )
+    }
+}
+
+/// Releases the capacity once the command finished, even if the command kept a handle to it.
+struct Held(Capacity);
+
+impl Drop for Held {
+    fn drop(&mut self) {
+        self.0.release();
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if self.is_admin {
+            Metrics::decrement(&self.metrics.admin_queue_depth);
+        } else {
+            Metrics::decrement(&self.metrics.queue_depth);
+        }
+    }
+}
+
+/// Counts a command as running until dropped, even if the command panics.
+struct Running(Arc<Metrics>);
+
+impl Running {
+    fn new(metrics: Arc<Metrics>) -> Self {
+        Metrics::increment(&metrics.running_commands);
+        Running(metrics)
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        Metrics::decrement(&self.0.running_commands);
+    }
+}
@@ -0,0 +1,253 @@
+use crate::context::CommandContext;
+use std::any::type_name;
+use std::future::Future;
+use thiserror::Error;
+use tracing::{debug, instrument};
+use twilight_model::channel::message::MessageFlags;
+use twilight_model::guild::PartialMember;
+use twilight_model::id::Id;
+use twilight_model::id::marker::GuildMarker;
+use twilight_util::builder::InteractionResponseDataBuilder;
+
+/// Reason an extractor could not produce its value, shown to the user.
+#[derive(Clone, Eq, PartialEq, Debug, Error)]
+pub enum Rejection {
+    #[error("This command can only be used in a server.")]
+    NotInGuild,
+    #[error("This command can only be used by server members.")]
+    NoMember,
+    #[error("Could not determine who used this command.")]
+    NoUser,
+    #[error("Could not determine the channel this command was used in.")]
+    NoChannel,
+    #[error("Could not determine your locale.")]
+    NoLocale,
+    #[error("This command is missing required state ({0}).")]
+    MissingState(&'static str),
+    #[error("This command is missing required request data ({0}).")]
+    MissingExtension(&'static str),
+    #[error("This command tried to read its options twice.")]
+    OptionsTaken,
+}
+
+#[derive(Debug, Error)]
+pub enum FnCommandError<HandlerError> {
+    #[error("Extractor rejected the command: {0}")]
+    Rejection(Rejection),
+    #[error("Handler error: {0}")]
+    Handler(HandlerError),
+}
+
+/// Everything an extractor can take its value from.
+#[derive(Debug)]
+pub struct CommandParts<TOptions> {
+    pub context: CommandContext,
+    pub options: Option<TOptions>,
+}
+
+impl<TOptions> CommandParts<TOptions> {
+    pub fn new(context: CommandContext, options: TOptions) -> Self {
+        CommandParts {
+            context,
+            options: Some(options),
+        }
+    }
+}
+
+pub trait FromCommandParts<TOptions>: Sized {
+    fn from_command_parts(parts: &mut CommandParts<TOptions>) -> Result<Self, Rejection>;
+}
+
+impl<TOptions> FromCommandParts<TOptions> for CommandContext {
+    fn from_command_parts(parts: &mut CommandParts<TOptions>) -> Result<Self, Rejection> {
+        Ok(parts.context.clone())
+    }
+}
+
+/// Makes an extractor optional instead of rejecting the command.
+impl<T, TOptions> FromCommandParts<TOptions> for Option<T>
+where
+    T: FromCommandParts<TOptions>,
+{
+    fn from_command_parts(parts: &mut CommandParts<TOptions>) -> Result<Self, Rejection> {
+        Ok(T::from_command_parts(parts).ok())
+    }
+}
+
+/// The parsed options of the command.
+#[derive(Clone, Debug)]
+pub struct Options<T>(pub T);
+
+impl<T> FromCommandParts<T> for Options<T> {
+    fn from_command_parts(parts: &mut CommandParts<T>) -> Result<Self, Rejection> {
+        parts
+            .options
+            .take()
+            .map(Options)
+            .ok_or(Rejection::OptionsTaken)
+    }
+}
+
+/// The user that invoked the command.
+#[derive(Clone, Debug)]
+pub struct User(pub twilight_model::user::User);
+
+impl<TOptions> FromCommandParts<TOptions> for User {
+    fn from_command_parts(parts: &mut CommandParts<TOptions>) -> Result<Self, Rejection> {
+        parts
+            .context
+            .interaction
+            .author()
+            .cloned()
+            .map(User)
+            .ok_or(Rejection::NoUser)
+    }
+}
+
+/// The guild member that invoked the command, rejects the command outside guilds.
+#[derive(Clone, Debug)]
+pub struct Member(pub PartialMember);
+
+impl<TOptions> FromCommandParts<TOptions> for Member {
+    fn from_command_parts(parts: &mut CommandParts<TOptions>) -> Result<Self, Rejection> {
+        parts
+            .context
+            .interaction
+            .member
+            .clone()
+            .map(Member)
+            .ok_or(Rejection::NoMember)
+    }
+}
+
+/// The guild the command was invoked in, rejects the command outside guilds.
+#[derive(Copy, Clone, Debug)]
+pub struct GuildId(pub Id<GuildMarker>);
+
+impl<TOptions> FromCommandParts<TOptions> for GuildId {
+    fn from_command_parts(parts: &mut CommandParts<TOptions>) -> Result<Self, Rejection> {
+        parts
+            .context
+            .interaction
+            .guild_id
+            .map(GuildId)
+            .ok_or(Rejection::NotInGuild)
+    }
+}
+
+/// The locale of the user that invoked the command.
+#[derive(Clone, Debug)]
+pub struct Locale(pub String);
+
+impl<TOptions> FromCommandParts<TOptions> for Locale {
+    fn from_command_parts(parts: &mut CommandParts<TOptions>) -> Result<Self, Rejection> {
+        parts
+            .context
+            .interaction
+            .locale
+            .clone()
+            .map(Locale)
+            .ok_or(Rejection::NoLocale)
+    }
+}
+
+/// A value taken from the application state extensions.
+#[derive(Clone, Debug)]
+pub struct FromState<T>(pub T);
+
+impl<T, TOptions> FromCommandParts<TOptions> for FromState<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    fn from_command_parts(parts: &mut CommandParts<TOptions>) -> Result<Self, Rejection> {
+        parts
+            .context
+            .state
+            .extensions
+            .get::<T>()
+            .cloned()
+            .map(FromState)
+            .ok_or(Rejection::MissingState(type_name::<T>()))
+    }
+}
+
+/// A value taken from the per request extensions, inserted by middleware.
+#[derive(Clone, Debug)]
+pub struct Extension<T>(pub T);
+
+impl<T, TOptions> FromCommandParts<TOptions> for Extension<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    fn from_command_parts(parts: &mut CommandParts<TOptions>) -> Result<Self, Rejection> {
+        parts
+            .context
+            .extensions
+            .get::<T>()
+            .cloned()
+            .map(Extension)
+            .ok_or(Rejection::MissingExtension(type_name::<T>()))
+    }
+}
+
+/// Async functions taking extractors as arguments, usable as command handlers.
+pub trait FnHandler<TArgs, TOptions> {
+    type Error;
+
+    fn call(
+        self,
+        parts: CommandParts<TOptions>,
+    ) -> impl Future<Output = Result<(), FnCommandError<Self::Error>>> + Send + 'static;
+}
+
+#[instrument(level = "debug", skip(context))]
+pub async fn reply_rejection(context: &CommandContext, rejection: &Rejection) {
+    let response = InteractionResponseDataBuilder::new()
+        .content(rejection.to_string())
+        .flags(MessageFlags::EPHEMERAL)
+        .build();
+    if let Err(error) = context.reply(response).await {
+        debug!(%error, "Could not reply with rejection");
+    }
+}
+
+macro_rules! impl_fn_handler {
+    ($($arg:ident),*) => {
+        impl<F, Fut, TError, TOptions, $($arg,)*> FnHandler<($($arg,)*), TOptions> for F
+        where
+            F: FnOnce($($arg,)*) -> Fut + Send + 'static,
+            Fut: Future<Output = Result<(), TError>> + Send + 'static,
+            TError: 'static,
+            TOptions: Send + 'static,
+            $($arg: FromCommandParts<TOptions> + Send + 'static,)*
+        {
+            type Error = TError;
+
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            async fn call(
+                self,
+                mut parts: CommandParts<TOptions>,
+            ) -> Result<(), FnCommandError<Self::Error>> {
+                $(let $arg = match $arg::from_command_parts(&mut parts) {
+                    Ok(value) => value,
+                    Err(rejection) => {
+                        reply_rejection(&parts.context, &rejection).await;
+                        return Err(FnCommandError::Rejection(rejection));
+                    }
+                };)*
+
+                self($($arg,)*).await.map_err(FnCommandError::Handler)
+            }
+        }
+    };
+}
+
+impl_fn_handler!();
+impl_fn_handler!(T1);
+impl_fn_handler!(T1, T2);
+impl_fn_handler!(T1, T2, T3);
+impl_fn_handler!(T1, T2, T3, T4);
+impl_fn_handler!(T1, T2, T3, T4, T5);
+impl_fn_handler!(T1, T2, T3, T4, T5, T6);
+impl_fn_handler!(T1, T2, T3, T4, T5, T6, T7);
+impl_fn_handler!(T1, T2, T3, T4, T5, T6, T7, T8);
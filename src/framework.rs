@@ -1,88 +1,151 @@
 use derive_where::derive_where;
+use futures::FutureExt;
 use futures::future::BoxFuture;
+use std::any::Any;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt::{Debug, Formatter};
 use std::marker::PhantomData;
-use std::pin::Pin;
+use std::ops::ControlFlow;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 use thiserror::Error;
-use tower::Service;
-use tracing::{Instrument, instrument, trace_span};
+use tower::util::{BoxCloneSyncService, MapErr, MapResponse};
+use tower::{BoxError, Layer, Service, ServiceExt, service_fn};
+use tracing::{Instrument, debug, error, instrument, trace_span};
+use twilight_gateway::EventTypeFlags;
 use twilight_interactions::command::CommandModel;
 use twilight_interactions::error::ParseError;
+use twilight_model::application::command::{Command, CommandType};
 use twilight_model::application::interaction::application_command::CommandData;
+use twilight_model::application::interaction::message_component::MessageComponentInteractionData;
 use twilight_model::application::interaction::{Interaction, InteractionData, InteractionType};
+use twilight_model::application::interaction::{InteractionContextType, InteractionMember};
+use twilight_model::channel::Message;
+use twilight_model::gateway::Intents;
+use twilight_model::gateway::event::Event;
+use twilight_model::gateway::payload::incoming::{GuildCreate, MemberAdd, MessageCreate, Ready};
+use twilight_model::id::Id;
+use twilight_model::id::marker::{GuildMarker, InteractionMarker, UserMarker};
+use twilight_model::user::User;
+use twilight_util::builder::command::CommandBuilder;
 
-#[derive(Clone, PartialEq, Debug, Error)]
+#[derive(Debug, Error)]
 pub enum Error<CommandError> {
     #[error("Creating command from Interaction failed: {0}")]
     FromInteraction(#[from] CommandFromInteractionError),
+    #[error("Context rejected the interaction: {0}")]
+    Rejected(BoxError),
     #[error("Command error: {0}")]
     Command(CommandError),
 }
 
+impl<CommandError> Error<CommandError>
+where
+    CommandError: std::error::Error + Send + Sync + 'static,
+{
+    /// Boxes the error for the router, keeping rejections recognizable as [`Rejected`].
+    fn boxed(self) -> BoxError {
+        match self {
+            Error::Rejected(rejection) => Box::new(Rejected(rejection)),
+            error => Box::new(error),
+        }
+    }
+}
+
+/// The context rejected the interaction after telling the user why, so it did not fail.
+#[derive(Debug, Error)]
+#[error("Context rejected the interaction: {0}")]
+pub struct Rejected(pub BoxError);
+
 #[derive(Clone, PartialEq, Debug, Error)]
 pub enum CommandFromInteractionError {
-    #[error("Interaction was not a command interaction")]
-    NotACommand(Interaction),
-    #[error("Interaction kind was ApplicationCommand, but no CommandData present")]
-    NoCommandData(Interaction, Option<InteractionData>),
-    #[error("Getting command from interaction failed: {1}")]
-    FromCommandData(Interaction, FromCommandDataError),
+    #[error("Interaction {0} of kind {1:?} has no matching data")]
+    MissingData(Id<InteractionMarker>, InteractionType),
+    #[error("Getting command from interaction {0} failed: {1}")]
+    Command(Id<InteractionMarker>, FromCommandDataError),
+    #[error("Getting component from interaction {0} failed: {1}")]
+    Component(Id<InteractionMarker>, FromComponentDataError),
+}
+
+/// Outcome of offering an interaction to a service.
+#[derive_where(Debug; TContextFactory: Debug, T: Debug)]
+pub enum Dispatch<TContextFactory, T = ()> {
+    Handled(T),
+    /// The interaction is not for this service and is handed back, so the next service can take it.
+    Unhandled(TContextFactory, Box<Interaction>),
 }
 
 #[derive(Clone, PartialEq, Debug, Error)]
 pub enum FromCommandDataError {
+    /// Makes the runner hand the interaction back as [`Dispatch::Unhandled`].
     #[error("Not registered command received")]
     UnknownCommand(Box<CommandData>),
     #[error("Command parse error: {0}")]
     Parse(#[from] ParseError),
+    #[error("Context menu command has no resolved target")]
+    MissingTarget,
 }
 
 #[derive_where(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Default, Debug, Hash)]
-pub struct CommandService<TCommand>(PhantomData<TCommand>);
+pub struct ExecutableCommandService<TCommand>(PhantomData<TCommand>);
 
-impl<TCommand> CommandService<TCommand> {
+impl<TCommand> ExecutableCommandService<TCommand> {
     pub fn new() -> Self {
-        CommandService::default()
+        ExecutableCommandService::default()
     }
 }
 
-impl<TCommand, TContext> Service<(TCommand, TContext)> for CommandService<TCommand>
+impl<TCommand, TContextFactory> Service<(TContextFactory, Interaction)>
+    for ExecutableCommandService<TCommand>
 where
-    TCommand: CommandHandler<Context = TContext>,
+    TCommand: CommandRunner<TContextFactory>,
 {
-    type Response = TCommand::Response;
-    type Error = TCommand::Error;
-    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+    type Response = Dispatch<TContextFactory, TCommand::Response>;
+    type Error = Error<TCommand::CommandError>;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
 
     fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         Poll::Ready(Ok(()))
     }
 
-    fn call(&mut self, (command, context): (TCommand, TContext)) -> Self::Future {
+    fn call(
+        &mut self,
+        (context_factory, interaction): (TContextFactory, Interaction),
+    ) -> Self::Future {
         Box::pin(
-            command
-                .handle(context)
-                .instrument(trace_span!("command handler")),
+            TCommand::run(context_factory, interaction).instrument(trace_span!("command runner")),
         )
     }
 }
 
+#[derive(Clone, PartialEq, Debug, Error)]
+pub enum FromComponentDataError {
+    /// Makes the runner hand the interaction back as [`Dispatch::Unhandled`].
+    #[error("Not registered component received: {}", .0.custom_id)]
+    UnknownComponent(Box<MessageComponentInteractionData>),
+    #[error("Invalid custom id arguments for component {0}: {1}")]
+    InvalidArguments(&'static str, String),
+}
+
 #[derive_where(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Default, Debug, Hash)]
-pub struct ExecutableCommandService<TCommand>(PhantomData<TCommand>);
+pub struct ExecutableComponentService<TComponent>(PhantomData<TComponent>);
 
-impl<TCommand> ExecutableCommandService<TCommand> {
+impl<TComponent> ExecutableComponentService<TComponent> {
     pub fn new() -> Self {
-        ExecutableCommandService::default()
+        ExecutableComponentService::default()
     }
 }
 
-impl<TCommand, TContextFactory> Service<(TContextFactory, Interaction)>
-    for ExecutableCommandService<TCommand>
+impl<TComponent, TContextFactory> Service<(TContextFactory, Interaction)>
+    for ExecutableComponentService<TComponent>
 where
-    TCommand: CommandRunner<TContextFactory>,
+    TComponent: ComponentRunner<TContextFactory>,
 {
-    type Response = TCommand::Response;
-    type Error = Error<TCommand::CommandError>;
+    type Response = Dispatch<TContextFactory, TComponent::Response>;
+    type Error = Error<TComponent::ComponentError>;
     type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
 
     fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
@@ -94,7 +157,8 @@ where
         (context_factory, interaction): (TContextFactory, Interaction),
     ) -> Self::Future {
         Box::pin(
-            TCommand::run(context_factory, interaction).instrument(trace_span!("command runner")),
+            TComponent::run(context_factory, interaction)
+                .instrument(trace_span!("component runner")),
         )
     }
 }
@@ -113,11 +177,113 @@ where
     }
 }
 
+/// What a context menu command was used on, resolved from [`CommandData::target_id`].
+pub trait ContextMenuTarget: Sized {
+    const KIND: CommandType;
+
+    fn from_command_data(command_data: &CommandData) -> Result<Self, FromCommandDataError>;
+}
+
+/// The user a user context menu command was used on.
+#[derive(Clone, Debug)]
+pub struct UserTarget {
+    pub user: User,
+    /// Present if the command was used in a guild.
+    pub member: Option<InteractionMember>,
+}
+
+impl ContextMenuTarget for UserTarget {
+    const KIND: CommandType = CommandType::User;
+
+    fn from_command_data(command_data: &CommandData) -> Result<Self, FromCommandDataError> {
+        let user_id = command_data
+            .target_id
+            .ok_or(FromCommandDataError::MissingTarget)?
+            .cast();
+        let resolved = command_data
+            .resolved
+            .as_ref()
+            .ok_or(FromCommandDataError::MissingTarget)?;
+        let user = resolved
+            .users
+            .get(&user_id)
+            .cloned()
+            .ok_or(FromCommandDataError::MissingTarget)?;
+        Ok(UserTarget {
+            user,
+            member: resolved.members.get(&user_id).cloned(),
+        })
+    }
+}
+
+/// The message a message context menu command was used on.
+#[derive(Clone, Debug)]
+pub struct MessageTarget {
+    pub message: Message,
+}
+
+impl ContextMenuTarget for MessageTarget {
+    const KIND: CommandType = CommandType::Message;
+
+    fn from_command_data(command_data: &CommandData) -> Result<Self, FromCommandDataError> {
+        let message_id = command_data
+            .target_id
+            .ok_or(FromCommandDataError::MissingTarget)?
+            .cast();
+        let message = command_data
+            .resolved
+            .as_ref()
+            .and_then(|resolved| resolved.messages.get(&message_id))
+            .cloned()
+            .ok_or(FromCommandDataError::MissingTarget)?;
+        Ok(MessageTarget { message })
+    }
+}
+
+/// A user or message context menu command, created from its target.
+pub trait ContextMenuCommand: Sized {
+    const NAME: &'static str;
+    type Target: ContextMenuTarget;
+
+    fn from_target(target: Self::Target) -> Self;
+
+    fn create_command() -> Command {
+        // Context menu commands have no description
+        CommandBuilder::new(Self::NAME, "", Self::Target::KIND).build()
+    }
+}
+
+/// A single component, identified by the part of its custom id before the first `:`.
+pub trait ComponentModel: Sized {
+    const NAME: &'static str;
+
+    /// Parses the component from the part of its custom id after the first `:`.
+    fn from_arguments(arguments: &str) -> Result<Self, FromComponentDataError>;
+
+    /// Arguments encoded into the custom id.
+    fn arguments(&self) -> String;
+
+    fn custom_id(&self) -> String {
+        format!("{}:{}", Self::NAME, self.arguments())
+    }
+}
+
+pub trait FromComponentData: Sized {
+    fn from_component_data(
+        component_data: Box<MessageComponentInteractionData>,
+    ) -> Result<Self, FromComponentDataError>;
+}
+
 pub trait CommandHandler {
     type Context;
     type Response;
     type Error;
 
+    /// Whether the command can only be used in the admin guild, checked before asking for confirmation.
+    const ADMIN_ONLY: bool = false;
+    /// Question the user has to confirm before the command is handled, if any.
+    const CONFIRMATION: Option<&'static str> = None;
+
     fn handle(
         self,
         context: Self::Context,
@@ -131,33 +297,68 @@ pub trait CommandRunner<ContextFactory> {
     fn run(
         context_factory: ContextFactory,
         interaction: Interaction,
-    ) -> impl Future<Output = Result<Self::Response, Error<Self::CommandError>>> + Send + 'static;
+    ) -> impl Future<
+        Output = Result<Dispatch<ContextFactory, Self::Response>, Error<Self::CommandError>>,
+    > + Send
+    + 'static;
+}
+
+pub trait ComponentRunner<ContextFactory> {
+    type Response;
+    type ComponentError;
+
+    fn run(
+        context_factory: ContextFactory,
+        interaction: Interaction,
+    ) -> impl Future<
+        Output = Result<Dispatch<ContextFactory, Self::Response>, Error<Self::ComponentError>>,
+    > + Send
+    + 'static;
+}
+
+/// Context handlers select as their [`CommandHandler::Context`], deciding where their commands can be used.
+pub trait HandlerContext {
+    /// Interaction contexts the commands are registered for, Discord's default if `None`.
+    const CONTEXTS: Option<&'static [InteractionContextType]>;
+
+    /// Restricts the command to the contexts it can be handled in.
+    fn register(mut command: Command) -> Command {
+        if let Some(contexts) = Self::CONTEXTS {
+            command.contexts = Some(contexts.to_vec());
+        }
+        command
+    }
 }
 
-pub trait CommandContextFactory {
-    type CommandContext;
+/// Creates the context of type `TContext` handlers select as their [`CommandHandler::Context`].
+pub trait CommandContextFactory<TContext> {
+    /// Reason the interaction can not get this context, after the user was told about it.
+    type Rejection: std::error::Error + Send + Sync + 'static;
 
-    fn create_context(self, interaction: Interaction) -> Self::CommandContext;
+    fn create_context(
+        self,
+        interaction: Interaction,
+    ) -> impl Future<Output = Result<TContext, Self::Rejection>> + Send;
 }
 
-impl<F, TCommandContext> CommandContextFactory for F
+impl<F, TContext> CommandContextFactory<TContext> for F
 where
-    F: FnOnce(Interaction) -> TCommandContext,
+    F: FnOnce(Interaction) -> TContext + Send,
 {
-    type CommandContext = TCommandContext;
+    type Rejection = Infallible;
 
     #[instrument(level = "trace", skip(self))]
-    fn create_context(self, interaction: Interaction) -> Self::CommandContext {
-        self(interaction)
+    async fn create_context(self, interaction: Interaction) -> Result<TContext, Self::Rejection> {
+        Ok(self(interaction))
     }
 }
 
 impl<TCommand, ContextFactory> CommandRunner<ContextFactory> for TCommand
 where
     TCommand: CommandHandler + FromCommandData,
-    TCommand: Sized + 'static,
+    TCommand: Sized + Send + 'static,
     TCommand::Context: Send,
-    ContextFactory: CommandContextFactory<CommandContext = TCommand::Context> + Send + 'static,
+    ContextFactory: CommandContextFactory<TCommand::Context> + Send + 'static,
 {
     type Response = TCommand::Response;
     type CommandError = <TCommand as CommandHandler>::Error;
@@ -166,36 +367,493 @@ where
     async fn run(
         context_factory: ContextFactory,
         mut interaction: Interaction,
-    ) -> Result<Self::Response, Error<Self::CommandError>> {
-        if !matches!(interaction.kind, InteractionType::ApplicationCommand) {
-            return Err(Error::FromInteraction(
-                CommandFromInteractionError::NotACommand(interaction),
-            ));
+    ) -> Result<Dispatch<ContextFactory, Self::Response>, Error<Self::CommandError>> {
+        // Autocomplete interactions carry partial command data, parsed by autocomplete models
+        if !matches!(
+            interaction.kind,
+            InteractionType::ApplicationCommand | InteractionType::ApplicationCommandAutocomplete
+        ) {
+            return Ok(Dispatch::Unhandled(context_factory, Box::new(interaction)));
         }
 
-        let command_data = match interaction.data.take() {
-            Some(InteractionData::ApplicationCommand(command_data)) => command_data,
-            data => {
-                return Err(Error::FromInteraction(
-                    CommandFromInteractionError::NoCommandData(interaction, data),
-                ));
-            }
+        let Some(InteractionData::ApplicationCommand(command_data)) = interaction.data.take()
+        else {
+            return Err(Error::FromInteraction(
+                CommandFromInteractionError::MissingData(interaction.id, interaction.kind),
+            ));
         };
 
         let command = match Self::from_command_data(command_data) {
             Ok(command) => command,
+            Err(FromCommandDataError::UnknownCommand(command_data)) => {
+                interaction.data = Some(InteractionData::ApplicationCommand(command_data));
+                return Ok(Dispatch::Unhandled(context_factory, Box::new(interaction)));
+            }
             Err(error) => {
                 return Err(Error::FromInteraction(
-                    CommandFromInteractionError::FromCommandData(interaction, error),
+                    CommandFromInteractionError::Command(interaction.id, error),
                 ));
             }
         };
 
-        let context = context_factory.create_context(interaction);
+        let context = context_factory
+            .create_context(interaction)
+            .await
+            .map_err(|rejection| Error::Rejected(Box::new(rejection)))?;
         command
             .handle(context)
             .instrument(trace_span!("command handler"))
             .await
+            .map(Dispatch::Handled)
+            .map_err(Error::Command)
+    }
+}
+
+impl<TComponent, ContextFactory> ComponentRunner<ContextFactory> for TComponent
+where
+    TComponent: CommandHandler + FromComponentData,
+    TComponent: Sized + Send + 'static,
+    TComponent::Context: Send,
+    ContextFactory: CommandContextFactory<TComponent::Context> + Send + 'static,
+{
+    type Response = TComponent::Response;
+    type ComponentError = <TComponent as CommandHandler>::Error;
+
+    #[tracing::instrument(level = "debug", skip(context_factory))]
+    async fn run(
+        context_factory: ContextFactory,
+        mut interaction: Interaction,
+    ) -> Result<Dispatch<ContextFactory, Self::Response>, Error<Self::ComponentError>> {
+        if !matches!(interaction.kind, InteractionType::MessageComponent) {
+            return Ok(Dispatch::Unhandled(context_factory, Box::new(interaction)));
+        }
+
+        let Some(InteractionData::MessageComponent(component_data)) = interaction.data.take()
+        else {
+            return Err(Error::FromInteraction(
+                CommandFromInteractionError::MissingData(interaction.id, interaction.kind),
+            ));
+        };
+
+        let component = match Self::from_component_data(component_data) {
+            Ok(component) => component,
+            Err(FromComponentDataError::UnknownComponent(component_data)) => {
+                interaction.data = Some(InteractionData::MessageComponent(component_data));
+                return Ok(Dispatch::Unhandled(context_factory, Box::new(interaction)));
+            }
+            Err(error) => {
+                return Err(Error::FromInteraction(
+                    CommandFromInteractionError::Component(interaction.id, error),
+                ));
+            }
+        };
+
+        let context = context_factory
+            .create_context(interaction)
+            .await
+            .map_err(|rejection| Error::Rejected(Box::new(rejection)))?;
+        component
+            .handle(context)
+            .instrument(trace_span!("component handler"))
+            .await
+            .map(Dispatch::Handled)
             .map_err(Error::Command)
     }
 }
+
+/// The interaction an after hook is called for.
+#[derive(Clone, Debug)]
+pub struct Invocation {
+    pub interaction_id: Id<InteractionMarker>,
+    pub kind: InteractionType,
+    /// Name of the command, or custom id of the component.
+    pub name: Option<String>,
+    pub user_id: Option<Id<UserMarker>>,
+    pub guild_id: Option<Id<GuildMarker>>,
+}
+
+impl Invocation {
+    pub fn new(interaction: &Interaction) -> Self {
+        let name = match &interaction.data {
+            Some(InteractionData::ApplicationCommand(data)) => Some(data.name.clone()),
+            Some(InteractionData::MessageComponent(data)) => Some(data.custom_id.clone()),
+            Some(InteractionData::ModalSubmit(data)) => Some(data.custom_id.clone()),
+            _ => None,
+        };
+        Invocation {
+            interaction_id: interaction.id,
+            kind: interaction.kind,
+            name,
+            user_id: interaction.author_id(),
+            guild_id: interaction.guild_id,
+        }
+    }
+}
+
+/// Runs before an interaction is routed.
+pub trait BeforeHook<TContextFactory>: Send + Sync {
+    /// Returns [`ControlFlow::Break`] to veto the interaction, after telling the user why.
+    fn before<'a>(
+        &'a self,
+        context_factory: &'a TContextFactory,
+        interaction: &'a Interaction,
+    ) -> BoxFuture<'a, ControlFlow<()>>;
+}
+
+/// A route panicked while handling the interaction, passed to the after hooks as its error.
+#[derive(Debug, Error)]
+#[error("Interaction handler panicked: {0}")]
+pub struct Panicked(pub String);
+
+/// The message of a panic payload, if it is a string.
+pub fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("[non-string panic payload]")
+}
+
+/// Runs after an interaction that was not vetoed was handled, or its route panicked.
+pub trait AfterHook: Send + Sync {
+    fn after<'a>(
+        &'a self,
+        invocation: &'a Invocation,
+        elapsed: Duration,
+        result: Result<(), &'a BoxError>,
+    ) -> BoxFuture<'a, ()>;
+}
+
+type Route<TContextFactory> =
+    BoxCloneSyncService<(TContextFactory, Interaction), Dispatch<TContextFactory>, BoxError>;
+
+/// Routes interactions to services by their [`InteractionType`].
+///
+/// Services of the same kind form a chain, an interaction is offered to each until one handles it,
+/// and to the fallback if none does.
+/// Errors of all routes are passed to a single error handler, which logs them by default.
+#[derive_where(Clone)]
+pub struct Router<TContextFactory> {
+    routes: Arc<HashMap<InteractionType, Vec<Route<TContextFactory>>>>,
+    fallback: Route<TContextFactory>,
+    on_error: Arc<dyn Fn(BoxError) + Send + Sync>,
+    before: Vec<Arc<dyn BeforeHook<TContextFactory>>>,
+    after: Vec<Arc<dyn AfterHook>>,
+}
+
+impl<TContextFactory> Debug for Router<TContextFactory> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Router")
+            .field("routes", &self.routes.keys())
+            .field("before", &self.before.len())
+            .field("after", &self.after.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<TContextFactory> Router<TContextFactory>
+where
+    TContextFactory: Send + 'static,
+{
+    /// Router without routes, ignoring every interaction.
+    pub fn new() -> Self {
+        Router {
+            routes: Arc::new(HashMap::new()),
+            fallback: Self::boxed(service_fn(
+                |(_, interaction): (TContextFactory, Interaction)| async move {
+                    debug!(kind = ?interaction.kind, "No route for interaction");
+                    Ok::<_, BoxError>(Dispatch::Handled(()))
+                },
+            )),
+            on_error: Arc::new(|error| error!(%error, "Interaction failed")),
+            before: Vec::new(),
+            after: Vec::new(),
+        }
+    }
+
+    fn boxed<S>(service: S) -> Route<TContextFactory>
+    where
+        S: Service<(TContextFactory, Interaction), Response = Dispatch<TContextFactory>>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        S::Error: Into<BoxError>,
+        S::Future: Send + 'static,
+    {
+        BoxCloneSyncService::new(MapErr::new(service, Into::into))
+    }
+
+    /// Adds the service to the end of the chain for interactions of the kind.
+    pub fn route<S>(mut self, kind: InteractionType, service: S) -> Self
+    where
+        S: Service<(TContextFactory, Interaction), Response = Dispatch<TContextFactory>>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        S::Error: Into<BoxError>,
+        S::Future: Send + 'static,
+    {
+        Arc::make_mut(&mut self.routes)
+            .entry(kind)
+            .or_default()
+            .push(Self::boxed(service));
+        self
+    }
+
+    /// Routes application commands to the command collection.
+    pub fn commands<TCommand>(self) -> Self
+    where
+        TCommand: CommandRunner<TContextFactory, Response = ()> + Send + Sync + 'static,
+        TCommand::CommandError: std::error::Error + Send + Sync + 'static,
+    {
+        self.route(
+            InteractionType::ApplicationCommand,
+            MapErr::new(ExecutableCommandService::<TCommand>::new(), Error::boxed),
+        )
+    }
+
+    /// Routes autocomplete requests to the collection of autocomplete models.
+    pub fn autocomplete<TAutocomplete>(self) -> Self
+    where
+        TAutocomplete: CommandRunner<TContextFactory, Response = ()> + Send + Sync + 'static,
+        TAutocomplete::CommandError: std::error::Error + Send + Sync + 'static,
+    {
+        self.route(
+            InteractionType::ApplicationCommandAutocomplete,
+            MapErr::new(
+                ExecutableCommandService::<TAutocomplete>::new(),
+                Error::boxed,
+            ),
+        )
+    }
+
+    /// Routes message components to the component collection.
+    pub fn components<TComponent>(self) -> Self
+    where
+        TComponent: ComponentRunner<TContextFactory, Response = ()> + Send + Sync + 'static,
+        TComponent::ComponentError: std::error::Error + Send + Sync + 'static,
+    {
+        self.route(
+            InteractionType::MessageComponent,
+            MapErr::new(
+                ExecutableComponentService::<TComponent>::new(),
+                Error::boxed,
+            ),
+        )
+    }
+
+    /// Wraps the fallback and all routes added so far in the layer.
+    ///
+    /// Routes added afterward are not wrapped, so middleware can be applied to some routes only.
+    pub fn layer<L>(mut self, layer: L) -> Self
+    where
+        L: Layer<Route<TContextFactory>>,
+        L::Service: Service<(TContextFactory, Interaction), Response = Dispatch<TContextFactory>>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        <L::Service as Service<(TContextFactory, Interaction)>>::Error: Into<BoxError>,
+        <L::Service as Service<(TContextFactory, Interaction)>>::Future: Send + 'static,
+    {
+        for chain in Arc::make_mut(&mut self.routes).values_mut() {
+            for route in chain {
+                *route = Self::boxed(layer.layer(route.clone()));
+            }
+        }
+        self.fallback = Self::boxed(layer.layer(self.fallback));
+        self
+    }
+
+    /// Handles errors returned by any route.
+    pub fn on_error(mut self, handler: impl Fn(BoxError) + Send + Sync + 'static) -> Self {
+        self.on_error = Arc::new(handler);
+        self
+    }
+
+    /// Adds a hook running before every interaction, in the order they were added.
+    pub fn before(mut self, hook: impl BeforeHook<TContextFactory> + 'static) -> Self {
+        self.before.push(Arc::new(hook));
+        self
+    }
+
+    /// Adds a hook running after every interaction, in the order they were added.
+    pub fn after(mut self, hook: impl AfterHook + 'static) -> Self {
+        self.after.push(Arc::new(hook));
+        self
+    }
+
+    /// Offers the interaction to the chain of its kind, then to the fallback.
+    async fn dispatch(
+        routes: Arc<HashMap<InteractionType, Vec<Route<TContextFactory>>>>,
+        fallback: Route<TContextFactory>,
+        mut request: (TContextFactory, Interaction),
+    ) -> Result<(), BoxError> {
+        let chain = routes.get(&request.1.kind).map_or(&[][..], Vec::as_slice);
+        for route in chain.iter().chain([&fallback]) {
+            match route.clone().oneshot(request).await? {
+                Dispatch::Handled(()) => return Ok(()),
+                Dispatch::Unhandled(context_factory, interaction) => {
+                    request = (context_factory, *interaction);
+                }
+            }
+        }
+        debug!(kind = ?request.1.kind, "Interaction unhandled by the fallback");
+        Ok(())
+    }
+
+    /// Handles interactions no route handled.
+    pub fn fallback<S>(mut self, service: S) -> Self
+    where
+        S: Service<(TContextFactory, Interaction), Response = ()> + Clone + Send + Sync + 'static,
+        S::Error: Into<BoxError>,
+        S::Future: Send + 'static,
+    {
+        self.fallback = Self::boxed(MapResponse::new(service, Dispatch::Handled));
+        self
+    }
+}
+
+impl<TContextFactory> Service<(TContextFactory, Interaction)> for Router<TContextFactory>
+where
+    TContextFactory: Send + 'static,
+{
+    type Response = ();
+    type Error = ();
+    type Future = BoxFuture<'static, Result<(), ()>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: (TContextFactory, Interaction)) -> Self::Future {
+        let router = self.clone();
+        Box::pin(
+            async move {
+                for hook in &router.before {
+                    if hook.before(&request.0, &request.1).await.is_break() {
+                        debug!("Interaction vetoed by hook");
+                        return Ok(());
+                    }
+                }
+
+                let invocation = (!router.after.is_empty()).then(|| Invocation::new(&request.1));
+                let started = Instant::now();
+                let dispatch =
+                    AssertUnwindSafe(Self::dispatch(router.routes, router.fallback, request));
+                let (result, panic) = match dispatch.catch_unwind().await {
+                    Ok(result) => (result, None),
+                    Err(panic) => {
+                        let error = Panicked(panic_message(panic.as_ref()).to_owned());
+                        (Err(error.into()), Some(panic))
+                    }
+                };
+                if let Some(invocation) = invocation {
+                    let elapsed = started.elapsed();
+                    for hook in &router.after {
+                        hook.after(&invocation, elapsed, result.as_ref().map(|&()| ()))
+                            .await;
+                    }
+                }
+                // The after hooks saw the failure, the caller reports the panic itself
+                if let Some(panic) = panic {
+                    panic::resume_unwind(panic);
+                }
+                result.map_err(|error| (router.on_error)(error))
+            }
+            .instrument(trace_span!("router")),
+        )
+    }
+}
+
+pub trait FromEvent: Sized {
+    /// Event types that have to be received from the gateway to produce this event.
+    const EVENT_TYPES: EventTypeFlags;
+    /// Intents required for the gateway to send this event.
+    const INTENTS: Intents;
+
+    fn from_event(event: Event) -> Option<Self>;
+}
+
+macro_rules! impl_from_event {
+    ($($event:ident with flags $flags:ident with intents $intents:expr;)*) => {
+        $(impl FromEvent for $event {
+            const EVENT_TYPES: EventTypeFlags = EventTypeFlags::$flags;
+            const INTENTS: Intents = $intents;
+
+            fn from_event(event: Event) -> Option<Self> {
+                match event {
+                    Event::$event(event) => Some(*event),
+                    _ => None,
+                }
+            }
+        })*
+    };
+}
+
+impl_from_event! {
+    GuildCreate with flags GUILD_CREATE with intents Intents::GUILDS;
+    MemberAdd with flags MEMBER_ADD with intents Intents::GUILD_MEMBERS;
+    MessageCreate
+        with flags MESSAGE_CREATE
+        with intents Intents::GUILD_MESSAGES.union(Intents::DIRECT_MESSAGES);
+    Ready with flags READY with intents Intents::empty();
+}
+
+pub trait EventHandler {
+    type Event: FromEvent;
+    type Context;
+    type Error;
+
+    fn handle(
+        event: Self::Event,
+        context: Self::Context,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send + 'static;
+}
+
+pub trait EventRunner<ContextFactory> {
+    type Error;
+
+    /// Event types that have to be received from the gateway for this runner.
+    const EVENT_TYPES: EventTypeFlags;
+    /// Intents required for the gateway to send all events for this runner.
+    const INTENTS: Intents;
+
+    fn run(
+        context_factory: ContextFactory,
+        event: Event,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send + 'static;
+}
+
+pub trait EventContextFactory {
+    type EventContext;
+
+    fn create_event_context(self) -> Self::EventContext;
+}
+
+impl<THandler, ContextFactory> EventRunner<ContextFactory> for THandler
+where
+    THandler: EventHandler + 'static,
+    THandler::Event: Send,
+    THandler::Context: Send,
+    ContextFactory: EventContextFactory<EventContext = THandler::Context> + Send + 'static,
+{
+    type Error = THandler::Error;
+
+    const EVENT_TYPES: EventTypeFlags = THandler::Event::EVENT_TYPES;
+    const INTENTS: Intents = THandler::Event::INTENTS;
+
+    #[tracing::instrument(level = "debug", skip_all, fields(event.kind = ?event.kind()))]
+    async fn run(context_factory: ContextFactory, event: Event) -> Result<(), Self::Error> {
+        let Some(event) = THandler::Event::from_event(event) else {
+            return Ok(());
+        };
+
+        let context = context_factory.create_event_context();
+        THandler::handle(event, context)
+            .instrument(trace_span!("event handler"))
+            .await
+    }
+}
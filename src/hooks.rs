@@ -0,0 +1,276 @@
+use crate::blocklist::BlockTarget;
+use crate::context::{AdminGuildId, CommandContext, ContextFactory};
+use crate::framework::{BeforeHook, CommandContextFactory};
+use crate::metrics::Metrics;
+use crate::settings::{GuildSettings, MAX_COOLDOWN_SECS};
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::sync::atomic::Ordering;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tracing::{debug, instrument};
+use twilight_model::application::interaction::{Interaction, InteractionData, InteractionType};
+use twilight_model::channel::message::MessageFlags;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{GuildMarker, UserMarker};
+use twilight_util::builder::InteractionResponseDataBuilder;
+
+/// Replies to the interaction with the reason it was vetoed.
+///
+/// Autocomplete interactions can not be answered with a message, so they are only vetoed.
+#[instrument(level = "debug", skip(context_factory, interaction), fields(interaction.id = %interaction.id))]
+pub async fn reply_veto(context_factory: &ContextFactory, interaction: &Interaction, reason: &str) {
+    if interaction.kind == InteractionType::ApplicationCommandAutocomplete {
+        return;
+    }
+
+    let Ok(context) = CommandContextFactory::<CommandContext>::create_context(
+        context_factory.clone(),
+        interaction.clone(),
+    )
+    .await;
+    let response = InteractionResponseDataBuilder::new()
+        .content(reason)
+        .flags(MessageFlags::EPHEMERAL)
+        .build();
+    if let Err(error) = context.reply(response).await {
+        debug!(%error, "Could not reply with veto");
+    }
+}
+
+/// Whether the interaction comes from the admin guild, which vetoes can not lock out.
+fn is_admin_guild(context_factory: &ContextFactory, interaction: &Interaction) -> bool {
+    let admin_guild_id = context_factory.state.extensions.get::<AdminGuildId>();
+    admin_guild_id
+        .is_some_and(|AdminGuildId(admin_guild_id)| interaction.guild_id == Some(*admin_guild_id))
+}
+
+/// Vetoes interactions received after shutdown was initiated.
+#[derive(Copy, Clone, Debug)]
+pub struct ShutdownVeto;
+
+impl BeforeHook<ContextFactory> for ShutdownVeto {
+    fn before<'a>(
+        &'a self,
+        context_factory: &'a ContextFactory,
+        interaction: &'a Interaction,
+    ) -> BoxFuture<'a, ControlFlow<()>> {
+        Box::pin(async move {
+            if !context_factory.state.shutdown.load(Ordering::Acquire) {
+                return ControlFlow::Continue(());
+            }
+
+            reply_veto(
+                context_factory,
+                interaction,
+                "The bot is shutting down, please try again in a moment.",
+            )
+            .await;
+            ControlFlow::Break(())
+        })
+    }
+}
+
+/// Vetoes interactions from blocked users and guilds, except in the admin guild.
+#[derive(Copy, Clone, Debug)]
+pub struct BlocklistVeto;
+
+impl BlocklistVeto {
+    /// Whether the interaction comes from a blocked user or guild outside the admin guild.
+    pub fn applies(context_factory: &ContextFactory, interaction: &Interaction) -> bool {
+        if is_admin_guild(context_factory, interaction) {
+            return false;
+        }
+        let user = interaction.author_id().map(BlockTarget::User);
+        let guild = interaction.guild_id.map(BlockTarget::Guild);
+        [user, guild]
+            .into_iter()
+            .flatten()
+            .any(|target| context_factory.state.blocklist.is_blocked(target))
+    }
+}
+
+impl BeforeHook<ContextFactory> for BlocklistVeto {
+    fn before<'a>(
+        &'a self,
+        context_factory: &'a ContextFactory,
+        interaction: &'a Interaction,
+    ) -> BoxFuture<'a, ControlFlow<()>> {
+        Box::pin(async move {
+            if !BlocklistVeto::applies(context_factory, interaction) {
+                return ControlFlow::Continue(());
+            }
+
+            Metrics::increment(&context_factory.state.metrics.blocked_interactions);
+            debug!("Interaction from blocked user or guild");
+            reply_veto(
+                context_factory,
+                interaction,
+                "You can not use this bot here.",
+            )
+            .await;
+            ControlFlow::Break(())
+        })
+    }
+}
+
+/// Vetoes interactions outside the admin guild while maintenance mode is on.
+#[derive(Copy, Clone, Debug)]
+pub struct MaintenanceVeto;
+
+impl MaintenanceVeto {
+    /// Whether maintenance mode is on and the interaction comes from outside the admin guild.
+    pub fn applies(context_factory: &ContextFactory, interaction: &Interaction) -> bool {
+        context_factory.state.maintenance.load(Ordering::Acquire)
+            && !is_admin_guild(context_factory, interaction)
+    }
+}
+
+impl BeforeHook<ContextFactory> for MaintenanceVeto {
+    fn before<'a>(
+        &'a self,
+        context_factory: &'a ContextFactory,
+        interaction: &'a Interaction,
+    ) -> BoxFuture<'a, ControlFlow<()>> {
+        Box::pin(async move {
+            if !MaintenanceVeto::applies(context_factory, interaction) {
+                return ControlFlow::Continue(());
+            }
+
+            Metrics::increment(&context_factory.state.metrics.maintenance_rejections);
+            reply_veto(
+                context_factory,
+                interaction,
+                "The bot is down for maintenance, please try again later.",
+            )
+            .await;
+            ControlFlow::Break(())
+        })
+    }
+}
+
+/// Vetoes commands and their autocompletion in guilds that disabled them.
+///
+/// The admin guild is exempt, so it can't lock itself out of the admin commands.
+#[derive(Copy, Clone, Debug)]
+pub struct DisabledCommands;
+
+impl BeforeHook<ContextFactory> for DisabledCommands {
+    fn before<'a>(
+        &'a self,
+        context_factory: &'a ContextFactory,
+        interaction: &'a Interaction,
+    ) -> BoxFuture<'a, ControlFlow<()>> {
+        Box::pin(async move {
+            let Some(InteractionData::ApplicationCommand(data)) = &interaction.data else {
+                return ControlFlow::Continue(());
+            };
+            if is_admin_guild(context_factory, interaction) {
+                return ControlFlow::Continue(());
+            }
+            let disabled = context_factory
+                .extensions
+                .get::<GuildSettings>()
+                .is_some_and(|settings| settings.disabled_commands.contains(&data.name));
+            if !disabled {
+                return ControlFlow::Continue(());
+            }
+
+            debug!(command = data.name, "Command is disabled in this guild");
+            reply_veto(
+                context_factory,
+                interaction,
+                "This command is disabled in this server.",
+            )
+            .await;
+            ControlFlow::Break(())
+        })
+    }
+}
+
+/// Command used by a user in a guild.
+type CooldownKey = (Id<GuildMarker>, Id<UserMarker>, String);
+
+/// Last uses of commands with a cooldown, stored in the [`State`](crate::context::State).
+#[derive(Default, Debug)]
+pub struct GuildCooldowns {
+    last_used: Mutex<HashMap<CooldownKey, Instant>>,
+}
+
+impl GuildCooldowns {
+    /// Records the use, returning how long the user still has to wait if the command is on cooldown.
+    fn try_use(&self, key: CooldownKey, cooldown: Duration) -> Option<Duration> {
+        let now = Instant::now();
+        let mut last_used = self
+            .last_used
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(used_at) = last_used.get(&key) {
+            let remaining = cooldown.saturating_sub(now.duration_since(*used_at));
+            if !remaining.is_zero() {
+                return Some(remaining);
+            }
+        }
+        last_used.insert(key, now);
+        None
+    }
+
+    /// Forgets uses older than the longest cooldown, which don't matter anymore.
+    pub fn prune(&self) {
+        let max_cooldown = Duration::from_secs(MAX_COOLDOWN_SECS);
+        let mut last_used = self
+            .last_used
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        last_used.retain(|_, used_at| used_at.elapsed() < max_cooldown);
+    }
+}
+
+/// Vetoes commands used again by the same user before the guild's cooldown for them passed.
+#[derive(Copy, Clone, Debug)]
+pub struct CooldownVeto;
+
+impl BeforeHook<ContextFactory> for CooldownVeto {
+    fn before<'a>(
+        &'a self,
+        context_factory: &'a ContextFactory,
+        interaction: &'a Interaction,
+    ) -> BoxFuture<'a, ControlFlow<()>> {
+        Box::pin(async move {
+            let (
+                InteractionType::ApplicationCommand,
+                Some(InteractionData::ApplicationCommand(data)),
+                Some(guild_id),
+                Some(user_id),
+            ) = (
+                interaction.kind,
+                &interaction.data,
+                interaction.guild_id,
+                interaction.author_id(),
+            )
+            else {
+                return ControlFlow::Continue(());
+            };
+            let cooldown = context_factory
+                .extensions
+                .get::<GuildSettings>()
+                .and_then(|settings| settings.cooldowns.get(&data.name));
+            let Some(&cooldown) = cooldown else {
+                return ControlFlow::Continue(());
+            };
+
+            let key = (guild_id, user_id, data.name.clone());
+            let cooldowns = context_factory.state.cooldowns();
+            let Some(remaining) = cooldowns.try_use(key, Duration::from_secs(cooldown)) else {
+                return ControlFlow::Continue(());
+            };
+            let reason = format!(
+                "This command is on cooldown, try again in {} seconds.",
+                remaining.as_secs().max(1)
+            );
+            reply_veto(context_factory, interaction, &reason).await;
+            ControlFlow::Break(())
+        })
+    }
+}
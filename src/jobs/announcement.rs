@@ -0,0 +1,26 @@
+use crate::commands::TwilightError;
+use crate::context::State;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::instrument;
+use twilight_model::id::Id;
+use twilight_model::id::marker::ChannelMarker;
+
+pub const KIND: &str = "announcement";
+
+/// Message sent to a channel at a later time.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Announcement {
+    pub channel_id: Id<ChannelMarker>,
+    pub content: String,
+}
+
+#[instrument(level = "info", skip(state))]
+pub async fn run(state: Arc<State>, announcement: Announcement) -> Result<(), TwilightError> {
+    state
+        .client
+        .create_message(announcement.channel_id)
+        .content(&announcement.content)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,12 @@
+use crate::context::State;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tracing::info;
+
+/// Every 15 minutes.
+pub const SCHEDULE: &str = "0 */15 * * * *";
+
+pub async fn run(state: Arc<State>) -> Result<(), Infallible> {
+    info!(metrics = ?state.metrics.snapshot(), "Metrics");
+    Ok(())
+}
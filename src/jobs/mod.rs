@@ -0,0 +1,20 @@
+use crate::scheduler::Scheduler;
+use crate::storage::Storage;
+
+pub mod announcement;
+mod log_metrics;
+mod prune_cooldowns;
+pub mod reminder;
+
+/// The scheduler with all of the bot's jobs registered.
+pub fn scheduler(storage: Storage) -> Result<Scheduler, cron::error::Error> {
+    Ok(Scheduler::new(storage)
+        .cron("log_metrics", log_metrics::SCHEDULE, log_metrics::run)?
+        .cron(
+            "prune_cooldowns",
+            prune_cooldowns::SCHEDULE,
+            prune_cooldowns::run,
+        )?
+        .one_shot(announcement::KIND, announcement::run)
+        .one_shot(reminder::KIND, reminder::run))
+}
@@ -0,0 +1,11 @@
+use crate::context::State;
+use std::convert::Infallible;
+use std::sync::Arc;
+
+/// Every 10 minutes.
+pub const SCHEDULE: &str = "0 */10 * * * *";
+
+pub async fn run(state: Arc<State>) -> Result<(), Infallible> {
+    state.cooldowns().prune();
+    Ok(())
+}
@@ -0,0 +1,179 @@
+use crate::commands::TwilightError;
+use crate::context::State;
+use crate::scheduler::{JobId, SchedulerError};
+use crate::storage::{Storage, StorageError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use thiserror::Error;
+use tracing::{instrument, warn};
+use twilight_http::api_error::ApiError;
+use twilight_http::error::ErrorType;
+use twilight_model::channel::message::AllowedMentions;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, UserMarker};
+
+pub const KIND: &str = "reminder";
+/// Most reminders a user can have pending at once.
+pub const MAX_PENDING: usize = 25;
+
+/// Discord error code for a channel that does not exist.
+const UNKNOWN_CHANNEL: u64 = 10003;
+/// Discord error code for a channel the bot can't see.
+const MISSING_ACCESS: u64 = 50001;
+/// Discord error code for lacking a permission, e.g. to send messages in the channel.
+const MISSING_PERMISSIONS: u64 = 50013;
+
+/// Message sent to a user at a later time.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Reminder {
+    pub user_id: Id<UserMarker>,
+    /// Channel the reminder is sent to, it is sent by direct message if left out.
+    pub channel_id: Option<Id<ChannelMarker>>,
+    pub message: String,
+}
+
+/// Reminder that has not been sent yet.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct PendingReminder {
+    pub id: JobId,
+    pub message: String,
+    pub due_at: DateTime<Utc>,
+}
+
+/// The user already has [`MAX_PENDING`] reminders, shown to the user.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Error)]
+#[error("You can have at most {MAX_PENDING} pending reminders.")]
+pub struct TooManyPending;
+
+/// Schedules the reminder, listing it among the user's pending reminders until it is sent.
+///
+/// The reminder is not scheduled if the user already has [`MAX_PENDING`] reminders.
+pub async fn schedule(
+    state: &State,
+    reminder: &Reminder,
+    due_at: DateTime<Utc>,
+) -> Result<Result<JobId, TooManyPending>, SchedulerError> {
+    let user_id = reminder.user_id.get().cast_signed();
+    let message = reminder.message.clone();
+    state
+        .scheduler()
+        .schedule_with(KIND, reminder, due_at, move |transaction, id| {
+            let pending: usize = transaction.query_row(
+                "SELECT COUNT(*) FROM reminders WHERE user_id = ?1",
+                [user_id],
+                |row| row.get(0),
+            )?;
+            if pending >= MAX_PENDING {
+                return Ok(Err(TooManyPending));
+            }
+            transaction.execute(
+                "INSERT INTO reminders (job_id, user_id, message, due_at) VALUES (?1, ?2, ?3, ?4)",
+                (id.0, user_id, message, due_at.timestamp_millis()),
+            )?;
+            Ok(Ok(()))
+        })
+        .await
+}
+
+/// The user's pending reminders, soonest first.
+pub async fn pending(
+    storage: &Storage,
+    user_id: Id<UserMarker>,
+) -> Result<Vec<PendingReminder>, StorageError> {
+    let user_id = user_id.get().cast_signed();
+    storage
+        .with_connection(move |connection| {
+            let mut statement = connection.prepare(
+                // Jobs retried after failing are due later than first scheduled
+                "SELECT reminders.job_id, reminders.message, scheduled_jobs.due_at FROM reminders
+                JOIN scheduled_jobs ON scheduled_jobs.id = reminders.job_id
+                WHERE reminders.user_id = ?1 ORDER BY scheduled_jobs.due_at",
+            )?;
+            let rows = statement
+                .query_map([user_id], |row| {
+                    Ok((JobId(row.get(0)?), row.get(1)?, row.get(2)?))
+                })?
+                .collect::<Result<Vec<(JobId, String, i64)>, _>>()?;
+            Ok(rows
+                .into_iter()
+                .filter_map(|(id, message, due_at)| {
+                    Some(PendingReminder {
+                        id,
+                        message,
+                        due_at: DateTime::from_timestamp_millis(due_at)?,
+                    })
+                })
+                .collect())
+        })
+        .await
+}
+
+/// Cancels the reminder if it belongs to the user, returning whether it was pending.
+#[instrument(level = "info", skip(state))]
+pub async fn cancel(
+    state: &State,
+    user_id: Id<UserMarker>,
+    id: JobId,
+) -> Result<bool, StorageError> {
+    let is_owner = pending(state.storage(), user_id)
+        .await?
+        .iter()
+        .any(|reminder| reminder.id == id);
+    if !is_owner {
+        return Ok(false);
+    }
+    state.scheduler().cancel(id).await
+}
+
+/// Whether the error means the channel was deleted or the bot may not send messages to it.
+fn is_unreachable_channel(error: &twilight_http::Error) -> bool {
+    matches!(
+        error.kind(),
+        ErrorType::Response {
+            error: ApiError::General(general),
+            ..
+        } if [UNKNOWN_CHANNEL, MISSING_ACCESS, MISSING_PERMISSIONS].contains(&general.code)
+    )
+}
+
+/// Sends the reminder, by direct message if its channel is gone or the bot may not send there.
+#[instrument(level = "info", skip(state))]
+pub async fn run(state: Arc<State>, reminder: Reminder) -> Result<(), TwilightError> {
+    if let Some(channel_id) = reminder.channel_id {
+        let allowed_mentions = AllowedMentions {
+            users: vec![reminder.user_id],
+            ..AllowedMentions::default()
+        };
+        let sent = state
+            .client
+            .create_message(channel_id)
+            .content(&format!(
+                "<@{}>, you asked to be reminded: {}",
+                reminder.user_id, reminder.message
+            ))
+            .allowed_mentions(Some(&allowed_mentions))
+            .await;
+        match sent {
+            Ok(_) => return Ok(()),
+            Err(error) if is_unreachable_channel(&error) => {
+                warn!(%error, "Could not send reminder to channel, sending it directly");
+            }
+            // Failing the job lets the scheduler retry transient errors
+            Err(error) => return Err(error.into()),
+        }
+    }
+
+    let channel = state
+        .client
+        .create_private_channel(reminder.user_id)
+        .await?
+        .model()
+        .await?;
+    state
+        .client
+        .create_message(channel.id)
+        .content(&format!("You asked to be reminded: {}", reminder.message))
+        .await?;
+    Ok(())
+}
@@ -1,141 +1,92 @@
 #![forbid(unsafe_code)]
 #![warn(clippy::pedantic)]
 
+mod audit;
+mod blocklist;
+mod cache;
+mod collectors;
 mod commands;
+mod components;
+mod config;
+mod confirm;
 mod context;
+mod events;
+mod executor;
+mod extensions;
+mod extract;
 mod framework;
+mod hooks;
+mod jobs;
+mod metrics;
+mod queue;
+mod retry;
+mod scheduler;
+mod sessions;
+mod settings;
+mod shards;
+mod signals;
+mod storage;
 mod util;
+mod when;
 
-use crate::commands::Commands;
-use crate::context::{ContextFactory, State};
-use crate::framework::{
-    CommandContextFactory, CommandFromInteractionError, Error, ExecutableCommandService,
+use crate::audit::AuditSink;
+use crate::blocklist::Blocklist;
+use crate::cache::Cache;
+use crate::collectors::Collectors;
+use crate::commands::{AdminCommands, Autocompletes, Commands, GuildCommands};
+use crate::components::Components;
+use crate::config::EnvConfig;
+use crate::context::{AdminGuildId, CommandContext, ContextFactory, RESTART_EXIT_CODE, State};
+use crate::events::Events;
+use crate::executor::CommandExecutor;
+use crate::extensions::Extensions;
+use crate::framework::{CommandContextFactory, EventRunner, Rejected, Router};
+use crate::hooks::{
+    BlocklistVeto, CooldownVeto, DisabledCommands, GuildCooldowns, MaintenanceVeto, ShutdownVeto,
 };
-use context::CommandContext;
-use serde::Deserialize;
-use std::future::Future;
-use std::ops::ControlFlow;
-use std::sync::Arc;
+use crate::metrics::Metrics;
+use crate::queue::{FileQueue, IdentifyQueue};
+use crate::retry::RetryPolicy;
+use crate::sessions::SessionStore;
+use crate::settings::SettingsCache;
+use crate::shards::{ShardSupervisor, SupervisorConfig};
+use crate::storage::Storage;
+use std::collections::BTreeMap;
+use std::process::ExitCode;
 use std::sync::atomic::{AtomicBool, Ordering};
-use tokio::signal;
-use tower::{Service, ServiceExt};
-use tracing::{Instrument, debug, error, info_span, instrument, warn};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tower::service_fn;
+use tower::timeout::TimeoutLayer;
+use tracing::{debug, error, info, instrument, warn};
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
-use twilight_gateway::error::ReceiveMessageError;
-use twilight_gateway::{Config, EventTypeFlags, Shard, StreamExt as _, create_recommended};
+use twilight_gateway::queue::InMemoryQueue;
+use twilight_gateway::{ConfigBuilder, Intents, create_iterator};
 use twilight_http::Client;
 use twilight_model::application::interaction::Interaction;
-use twilight_model::gateway::Intents;
-use twilight_model::gateway::event::Event;
-use twilight_model::id::Id;
-use twilight_model::id::marker::{ApplicationMarker, GuildMarker};
+use twilight_model::channel::message::MessageFlags;
+use twilight_util::builder::InteractionResponseDataBuilder;
 
-#[instrument(level = "info", fields(shard.id = %shard.id()), skip(router, shard))]
-async fn shard_runner(
-    router: impl Service<
-        (ContextFactory, Interaction),
-        Response = (),
-        Error = (),
-        Future = impl Future<Output = Result<(), ()>> + Send,
-    > + Clone
-    + Send
-    + 'static,
-    context_factory: ContextFactory,
-    mut shard: Shard,
-) {
-    while let Some(event) = shard.next_event(EventTypeFlags::INTERACTION_CREATE).await {
-        if let ControlFlow::Break(()) =
-            handle_event(router.clone(), context_factory.clone(), event).await
-        {
-            break;
-        }
-    }
-}
+/// Time running commands and scheduled jobs get to finish after the shards shut down.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
 
-#[instrument(level = "debug", skip(router))]
-async fn handle_event(
-    mut router: impl Service<
-        (ContextFactory, Interaction),
-        Response = (),
-        Error = (),
-        Future = impl Future<Output = Result<(), ()>> + Send,
-    > + Clone
-    + Send
-    + 'static,
-    context_factory: ContextFactory,
-    event: Result<Event, ReceiveMessageError>,
-) -> ControlFlow<()> {
-    fn assert_fully_processed<Fut: Future<Output = Result<(), ()>>>(it: Fut) -> Fut {
-        it
-    }
-
-    let interaction = match event {
-        Ok(Event::InteractionCreate(interaction_create)) => interaction_create.0,
-        Ok(Event::GatewayClose(close_frame))
-            if context_factory.state.shutdown.load(Ordering::Acquire) =>
-        {
-            // TODO: Some kind of timeout for shutdown
-            debug!(?close_frame, "GatewayClose after shutdown");
-            return ControlFlow::Break(());
-        }
-        Err(error) => {
-            warn!(%error, "Error receiving gateway event");
-            return ControlFlow::Continue(());
-        }
-        _ => return ControlFlow::Continue(()),
-    };
-
-    // TODO: Commands probably need to be abortable? Right now they'd be just cut off when the application exits
-    tokio::spawn(assert_fully_processed(
-        async move {
-            router
-                .ready()
-                .await?
-                .call((context_factory, interaction))
-                .await
-        }
-        .instrument(info_span!("command service execution")),
-    ));
-
-    ControlFlow::Continue(())
-}
-
-fn get_command_router<TContextFactory>() -> impl Service<
-    (TContextFactory, Interaction),
-    Response = (),
-    Error = (),
-    Future = impl Future<Output = Result<(), ()>> + Send,
-> + Clone
-+ Send
-+ 'static
-where
-    TContextFactory: CommandContextFactory<CommandContext = CommandContext> + Send + 'static,
-{
-    let service = ExecutableCommandService::<Commands>::new();
-    // UFCS because the type hint for TContextFactory is required and other constructs require nightly
-    <ExecutableCommandService<_> as ServiceExt<(TContextFactory, _)>>::map_err(service, |error| {
-        match &error {
-            Error::FromInteraction(
-                CommandFromInteractionError::FromCommandData(_, _)
-                | CommandFromInteractionError::NoCommandData(_, _),
-            )
-            | Error::Command(_) => error!(%error),
-            Error::FromInteraction(CommandFromInteractionError::NotACommand(_)) => {
-                debug!(%error);
-            }
-        }
-    })
-}
-
-#[instrument]
-async fn ctrl_c_handler(state: &State) {
-    if let Err(error) = signal::ctrl_c().await {
-        error!(%error, "Could not install ctrl-c handler, sending shutdown");
-    }
-    if let Err(error) = state.send_shutdown() {
-        error!(?error, "Sending shutdown from ctrl-c handler failed");
-    }
+#[instrument(level = "debug", skip(context_factory))]
+async fn unsupported_interaction(
+    (context_factory, interaction): (ContextFactory, Interaction),
+) -> Result<(), twilight_http::Error> {
+    debug!(kind = ?interaction.kind, "Unsupported interaction");
+    let Ok(context) =
+        CommandContextFactory::<CommandContext>::create_context(context_factory, interaction).await;
+    context
+        .reply(
+            InteractionResponseDataBuilder::new()
+                .content("This interaction is not supported.")
+                .flags(MessageFlags::EPHEMERAL)
+                .build(),
+        )
+        .await?;
+    Ok(())
 }
 
 pub fn install_tracing() {
@@ -149,59 +100,169 @@ pub fn install_tracing() {
         .init();
 }
 
-#[derive(Deserialize)]
-struct EnvConfig {
-    pub discord_token: String,
-    pub application_id: Id<ApplicationMarker>,
-    pub admin_guild_id: Id<GuildMarker>,
-}
-
 // TODO: This should probably return () after proper tracing is set up
 // TODO: Also break up this function also use envy
 #[tokio::main]
 #[instrument]
-async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+async fn main() -> Result<ExitCode, Box<dyn std::error::Error + Send + Sync>> {
     _ = dotenv::dotenv();
     install_tracing();
 
     let config: EnvConfig = envy::from_env()
         .inspect_err(|error| error!(%error, "Error reading config from environment"))?;
+    config
+        .validate()
+        .inspect_err(|error| error!(%error, "Invalid config"))?;
+    let layout = config
+        .shard_layout()
+        .inspect_err(|error| error!(%error, "Invalid shard layout"))?;
+    let executor_config = config.executor_config();
 
     let client = Client::new(config.discord_token.clone());
     let interaction = client.interaction(config.application_id);
-    Commands::update_commands(&interaction, config.admin_guild_id).await?;
+    let diff = Commands::update_commands(&interaction, config.admin_guild_id).await?;
+    info!(%diff, "Updated commands");
 
-    let shard_config = Config::new(config.discord_token, Intents::empty());
-    let shards: Vec<_> = create_recommended(&client, shard_config, |_, builder| builder.build())
-        .await?
+    let gateway_info = client.gateway().authed().await?.model().await?;
+    let limit = gateway_info.session_start_limit;
+    let queue = match &config.identify_queue_dir {
+        Some(directory) => {
+            IdentifyQueue::File(FileQueue::new(directory.clone(), limit.max_concurrency)?)
+        }
+        None => IdentifyQueue::InMemory(InMemoryQueue::new(
+            limit.max_concurrency,
+            limit.remaining,
+            Duration::from_millis(limit.reset_after),
+            limit.total,
+        )),
+    };
+    let cache = Cache::new(&config.cache_resources);
+    let shard_config = ConfigBuilder::new(
+        config.discord_token.clone(),
+        <Events as EventRunner<ContextFactory>>::INTENTS
+            | cache.as_ref().map_or(Intents::empty(), Cache::intents),
+    )
+    .queue(queue)
+    .build();
+
+    let total = layout.total(gateway_info.shards);
+    let range = layout
+        .non_empty_shards(total)
+        .inspect_err(|error| error!(%error, "Invalid shard layout"))?;
+    info!(total, ?range, "Starting shards");
+    let saved_sessions = SessionStore::take_from_file(&config.session_file)
+        .inspect_err(|error| warn!(%error, "Could not load saved sessions, identifying instead"))
+        .unwrap_or_default();
+    info!(sessions = saved_sessions.len(), "Loaded saved sessions");
+    let shards: Vec<_> =
+        create_iterator(range, total, shard_config.clone(), |shard_id, builder| {
+            saved_sessions.configure_shard(shard_id, builder).build()
+        })
         .collect();
-    let senders: Vec<_> = shards.iter().map(Shard::sender).collect();
 
-    let router = get_command_router();
+    let storage = Storage::open(&config.database_file, config.database_pool_size)
+        .inspect_err(|error| error!(%error, "Could not open storage"))?;
+    let blocklist = Blocklist::load(storage.clone())
+        .await
+        .inspect_err(|error| error!(%error, "Could not load blocklist"))?;
+    let scheduler = jobs::scheduler(storage.clone())
+        .inspect_err(|error| error!(%error, "Invalid job schedule"))?;
+    let metrics = Arc::new(Metrics::default());
+    let config = Arc::new(config);
+    let mut extensions = Extensions::new()
+        .with(AdminGuildId(config.admin_guild_id))
+        .with(SettingsCache::new(storage.clone()))
+        .with(storage)
+        .with(GuildCooldowns::default())
+        .with(scheduler);
+    if let Some(cache) = cache {
+        extensions.insert(cache);
+    }
     let state = Arc::new(State {
         client,
-        senders: senders.clone(),
+        senders: RwLock::new(BTreeMap::new()),
         app_id: config.application_id,
         shutdown: AtomicBool::new(false),
+        restart: AtomicBool::new(false),
+        config: RwLock::new(config.clone()),
+        executor: CommandExecutor::new(executor_config, metrics.clone()),
+        metrics,
+        retry_policy: RetryPolicy::default(),
+        collectors: Collectors::default(),
+        maintenance: AtomicBool::new(false),
+        blocklist,
+        extensions,
     });
-    let runners: Vec<_> = shards
-        .into_iter()
-        .map(|shard| {
-            let router = router.clone();
-            let context_factory = ContextFactory::new(state.clone());
-            tokio::spawn(shard_runner(router, context_factory, shard))
-        })
-        .collect();
+    let audit = AuditSink::new(state.clone());
+    let router = Router::new()
+        .commands::<Commands>()
+        .commands::<AdminCommands>()
+        .commands::<GuildCommands>()
+        .autocomplete::<Autocompletes>()
+        .components::<Components>()
+        .fallback(service_fn(unsupported_interaction))
+        .before(ShutdownVeto)
+        .before(BlocklistVeto)
+        .before(MaintenanceVeto)
+        .before(DisabledCommands)
+        .before(CooldownVeto)
+        .after(audit.clone())
+        // Nothing can be sent for the interaction after its token expired
+        .layer(TimeoutLayer::new(retry::TOKEN_DEADLINE))
+        .on_error({
+            let metrics = state.metrics.clone();
+            move |error| {
+                if error.is::<Rejected>() {
+                    Metrics::increment(&metrics.rejected_commands);
+                    debug!(%error, "Interaction rejected");
+                    return;
+                }
+                Metrics::increment(&metrics.failed_interactions);
+                error!(%error, "Interaction failed");
+            }
+        });
+    let supervisor_config = SupervisorConfig {
+        reshard_interval: Duration::from_secs(config.reshard_interval_secs),
+        ..SupervisorConfig::default()
+    };
+    let supervisor = ShardSupervisor::new(
+        state.clone(),
+        router,
+        shard_config,
+        layout,
+        supervisor_config,
+    );
 
-    tokio::spawn(async move {
-        ctrl_c_handler(&state).await;
+    tokio::spawn({
+        let state = state.clone();
+        async move {
+            signals::handle_signals(&state).await;
+        }
     });
 
-    for runner in runners {
-        if let Err(error) = runner.await {
-            error!(%error, "Runner exited unexpectedly");
+    let scheduler = tokio::spawn({
+        let state = state.clone();
+        async move {
+            state.scheduler().run(&state, DRAIN_TIMEOUT).await;
         }
+    });
+
+    let sessions = supervisor.run(shards).await;
+    if let Err(error) = sessions.save_to_file(&config.session_file) {
+        error!(%error, "Could not save sessions, shards will identify on next start");
+    }
+    state.scheduler().stop();
+    let (_, scheduler) = tokio::join!(state.executor.drain(DRAIN_TIMEOUT), scheduler);
+    if let Err(error) = scheduler {
+        error!(%error, "Scheduler task failed");
     }
+    // Commands finishing while draining queue audit entries, so this has to come after
+    audit.flush(DRAIN_TIMEOUT).await;
+    info!(metrics = ?state.metrics.snapshot(), "Shut down");
 
-    Ok(())
+    if state.restart.load(Ordering::Acquire) {
+        info!(code = RESTART_EXIT_CODE, "Exiting for restart");
+        return Ok(ExitCode::from(RESTART_EXIT_CODE));
+    }
+    Ok(ExitCode::SUCCESS)
 }
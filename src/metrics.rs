@@ -0,0 +1,57 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Process wide counters and gauges.
+#[derive(Default, Debug)]
+pub struct Metrics {
+    /// Gauge of commands waiting for execution capacity.
+    pub queue_depth: AtomicU64,
+    /// Gauge of admin guild commands waiting for execution capacity, which are not limited by the queue.
+    pub admin_queue_depth: AtomicU64,
+    /// Gauge of commands currently executing.
+    pub running_commands: AtomicU64,
+    /// Commands rejected because the queue was full or they can't be used where they were used.
+    pub rejected_commands: AtomicU64,
+    /// Commands that panicked while executing.
+    pub command_panics: AtomicU64,
+    /// Interactions whose route returned an error.
+    pub failed_interactions: AtomicU64,
+    /// Interactions rejected because the user or guild is blocked.
+    pub blocked_interactions: AtomicU64,
+    /// Interactions rejected because of maintenance mode.
+    pub maintenance_rejections: AtomicU64,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct MetricsSnapshot {
+    pub queue_depth: u64,
+    pub admin_queue_depth: u64,
+    pub running_commands: u64,
+    pub rejected_commands: u64,
+    pub command_panics: u64,
+    pub failed_interactions: u64,
+    pub blocked_interactions: u64,
+    pub maintenance_rejections: u64,
+}
+
+impl Metrics {
+    pub fn increment(metric: &AtomicU64) -> u64 {
+        metric.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub fn decrement(metric: &AtomicU64) -> u64 {
+        metric.fetch_sub(1, Ordering::Relaxed)
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            queue_depth: self.queue_depth.load(Ordering::Relaxed),
+            admin_queue_depth: self.admin_queue_depth.load(Ordering::Relaxed),
+            running_commands: self.running_commands.load(Ordering::Relaxed),
+            rejected_commands: self.rejected_commands.load(Ordering::Relaxed),
+            command_panics: self.command_panics.load(Ordering::Relaxed),
+            failed_interactions: self.failed_interactions.load(Ordering::Relaxed),
+            blocked_interactions: self.blocked_interactions.load(Ordering::Relaxed),
+            maintenance_rejections: self.maintenance_rejections.load(Ordering::Relaxed),
+        }
+    }
+}
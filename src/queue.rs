@@ -0,0 +1,99 @@
+use std::fs::OpenOptions;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::oneshot;
+use tracing::{instrument, trace, warn};
+use twilight_gateway::queue::{IDENTIFY_DELAY, InMemoryQueue, Queue};
+
+/// Identify queue used by the shards of this process.
+#[derive(Clone, Debug)]
+pub enum IdentifyQueue {
+    /// Only coordinates the shards of this process.
+    InMemory(InMemoryQueue),
+    /// Coordinates all processes on this host using the same directory.
+    File(FileQueue),
+}
+
+impl Queue for IdentifyQueue {
+    fn enqueue(&self, id: u32) -> oneshot::Receiver<()> {
+        match self {
+            IdentifyQueue::InMemory(queue) => queue.enqueue(id),
+            IdentifyQueue::File(queue) => queue.enqueue(id),
+        }
+    }
+}
+
+/// Stand-in for a shared identify queue service, using lock files in a local directory.
+///
+/// There is one lock file per rate limit bucket, containing the time of the last identify in that bucket.
+/// Processes hold the lock while waiting for their turn, so identifies are spaced out across processes.
+/// The daily session start limit is not tracked.
+#[derive(Clone, Debug)]
+pub struct FileQueue {
+    directory: Arc<PathBuf>,
+    max_concurrency: u16,
+}
+
+impl FileQueue {
+    pub fn new(directory: PathBuf, max_concurrency: u16) -> std::io::Result<Self> {
+        std::fs::create_dir_all(&directory)?;
+        Ok(FileQueue {
+            directory: Arc::new(directory),
+            max_concurrency: max_concurrency.max(1),
+        })
+    }
+}
+
+impl Queue for FileQueue {
+    fn enqueue(&self, id: u32) -> oneshot::Receiver<()> {
+        let (sender, receiver) = oneshot::channel();
+        let bucket = id % u32::from(self.max_concurrency);
+        let path = self.directory.join(format!("bucket-{bucket}.lock"));
+
+        tokio::task::spawn_blocking(move || {
+            if let Err(error) = wait_for_turn(&path) {
+                warn!(%error, shard.number = id, "Identify queue failed, identifying uncoordinated");
+            }
+            // The shard not waiting anymore is not a problem
+            _ = sender.send(());
+        });
+
+        receiver
+    }
+}
+
+#[instrument(level = "trace")]
+fn wait_for_turn(path: &Path) -> std::io::Result<()> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+    // Released when the file is closed
+    file.lock()?;
+
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    if let Ok(last_identify) = contents.trim().parse::<u64>() {
+        let last_identify = UNIX_EPOCH + Duration::from_millis(last_identify);
+        if let Ok(elapsed) = SystemTime::now().duration_since(last_identify)
+            && let Some(remaining) = IDENTIFY_DELAY.checked_sub(elapsed)
+        {
+            trace!(?remaining, "Waiting for identify slot");
+            std::thread::sleep(remaining);
+        }
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    write!(file, "{now}")?;
+
+    Ok(())
+}
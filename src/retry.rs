@@ -0,0 +1,345 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, instrument, warn};
+use twilight_http::Response;
+use twilight_http::api_error::ApiError;
+use twilight_http::error::ErrorType;
+use twilight_http::response::marker::EmptyBody;
+use twilight_model::id::Id;
+use twilight_model::id::marker::InteractionMarker;
+
+/// Milliseconds between the unix epoch and the discord epoch.
+const DISCORD_EPOCH_MS: u64 = 1_420_070_400_000;
+/// Time after creation an interaction has to be responded to.
+const RESPONSE_DEADLINE: Duration = Duration::from_secs(3);
+/// Time after creation an interaction token can be used for followups.
+pub const TOKEN_DEADLINE: Duration = Duration::from_mins(15);
+
+/// Discord error code for an interaction that does not exist or expired.
+const UNKNOWN_INTERACTION: u64 = 10062;
+/// Discord error code for responding to an interaction twice.
+const ALREADY_ACKNOWLEDGED: u64 = 40060;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ErrorClass {
+    /// The request might succeed when sent again, after `retry_after` if known.
+    Transient { retry_after: Option<Duration> },
+    /// The interaction expired or never existed.
+    UnknownInteraction,
+    /// The interaction was already responded to.
+    AlreadyAcknowledged,
+    /// Sending the request again would fail the same way.
+    Permanent,
+}
+
+impl ErrorClass {
+    pub fn classify(error: &twilight_http::Error) -> Self {
+        match error.kind() {
+            ErrorType::RequestError
+            | ErrorType::RequestTimedOut
+            | ErrorType::ServiceUnavailable { .. } => ErrorClass::Transient { retry_after: None },
+            ErrorType::Response {
+                error: ApiError::Ratelimited(ratelimited),
+                ..
+            } => ErrorClass::Transient {
+                retry_after: Duration::try_from_secs_f64(ratelimited.retry_after).ok(),
+            },
+            ErrorType::Response {
+                error: ApiError::General(general),
+                ..
+            } if general.code == UNKNOWN_INTERACTION => ErrorClass::UnknownInteraction,
+            ErrorType::Response {
+                error: ApiError::General(general),
+                ..
+            } if general.code == ALREADY_ACKNOWLEDGED => ErrorClass::AlreadyAcknowledged,
+            ErrorType::Response { status, .. } if status.is_server_error() => {
+                ErrorClass::Transient { retry_after: None }
+            }
+            _ => ErrorClass::Permanent,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct RetryPolicy {
+    /// Attempts including the first one.
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the attempt after `attempt`, doubling each time up to the maximum.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2_u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay)
+    }
+
+    /// Sends the request until it succeeds, fails permanently, or the next attempt would miss the deadline.
+    #[instrument(level = "debug", skip(self, request))]
+    pub async fn retry<T, Fut>(
+        &self,
+        deadline: SystemTime,
+        mut request: impl FnMut() -> Fut,
+    ) -> Result<T, twilight_http::Error>
+    where
+        Fut: Future<Output = Result<T, twilight_http::Error>>,
+    {
+        let mut attempt = 1;
+        loop {
+            let error = match request().await {
+                Ok(response) => return Ok(response),
+                Err(error) => error,
+            };
+
+            let delay = match ErrorClass::classify(&error) {
+                ErrorClass::Transient { retry_after } => {
+                    retry_after.unwrap_or_else(|| self.backoff(attempt))
+                }
+                class => {
+                    debug!(?class, %error, "Request failed permanently");
+                    return Err(error);
+                }
+            };
+
+            if attempt >= self.max_attempts {
+                warn!(attempt, %error, "Request failed, giving up after too many attempts");
+                return Err(error);
+            }
+            if SystemTime::now() + delay >= deadline {
+                warn!(attempt, %error, "Request failed, retrying would miss the deadline");
+                return Err(error);
+            }
+
+            debug!(attempt, ?delay, %error, "Request failed, retrying");
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Creates an interaction response like [`RetryPolicy::retry`].
+    ///
+    /// Creating a response is not idempotent, an attempt that failed transiently may still have
+    /// reached Discord. The interaction being already acknowledged after such a failure means
+    /// an earlier attempt succeeded.
+    pub async fn respond<Fut>(
+        &self,
+        deadline: SystemTime,
+        mut request: impl FnMut() -> Fut,
+    ) -> Result<(), twilight_http::Error>
+    where
+        Fut: Future<Output = Result<Response<EmptyBody>, twilight_http::Error>>,
+    {
+        let failed_transiently = AtomicBool::new(false);
+        let result = self
+            .retry(deadline, || {
+                let attempt = request();
+                let failed_transiently = &failed_transiently;
+                async move {
+                    let result = attempt.await;
+                    if let Err(error) = &result
+                        && matches!(ErrorClass::classify(error), ErrorClass::Transient { .. })
+                    {
+                        failed_transiently.store(true, Ordering::Relaxed);
+                    }
+                    result
+                }
+            })
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(error)
+                if failed_transiently.load(Ordering::Relaxed)
+                    && ErrorClass::classify(&error) == ErrorClass::AlreadyAcknowledged =>
+            {
+                debug!(%error, "Response was already created by an earlier attempt");
+                Ok(())
+            }
+            Err(error) => Err(error),
+        }
+    }
+}
+
+/// Time the interaction was created at, as encoded in its ID.
+pub fn interaction_created_at(id: Id<InteractionMarker>) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis((id.get() >> 22) + DISCORD_EPOCH_MS)
+}
+
+/// Deadline for the initial response to an interaction.
+pub fn response_deadline(id: Id<InteractionMarker>) -> SystemTime {
+    interaction_created_at(id) + RESPONSE_DEADLINE
+}
+
+/// Deadline for followups and edits using the interaction token.
+pub fn token_deadline(id: Id<InteractionMarker>) -> SystemTime {
+    interaction_created_at(id) + TOKEN_DEADLINE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::IntoFuture;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::AtomicU32;
+    use std::thread;
+    use twilight_http::Client;
+
+    /// Client sending its requests to a local server, which answers them with the response.
+    fn client(response: Option<(&str, &str)>) -> Client {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let response = response.map(|(status, body)| {
+            format!(
+                "HTTP/1.1 {status}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            )
+        });
+        thread::spawn(move || {
+            // Connections are kept open, so requests without a response time out
+            let mut connections = Vec::new();
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    return;
+                };
+                _ = stream.read(&mut [0; 4096]);
+                if let Some(response) = &response {
+                    _ = stream.write_all(response.as_bytes());
+                }
+                connections.push(stream);
+            }
+        });
+        Client::builder()
+            .proxy(address.to_string(), true)
+            .ratelimiter(None)
+            .timeout(Duration::from_millis(200))
+            .build()
+    }
+
+    async fn classify(response: Option<(&str, &str)>) -> ErrorClass {
+        let error = client(response).gateway().await.unwrap_err();
+        ErrorClass::classify(&error)
+    }
+
+    #[tokio::test]
+    async fn classify_errors() {
+        let transient = ErrorClass::Transient { retry_after: None };
+        assert_eq!(
+            classify(Some((
+                "429 Too Many Requests",
+                r#"{"global":false,"message":"You are being rate limited.","retry_after":1.5}"#
+            )))
+            .await,
+            ErrorClass::Transient {
+                retry_after: Some(Duration::from_millis(1500))
+            }
+        );
+        assert_eq!(
+            classify(Some((
+                "500 Internal Server Error",
+                r#"{"code":0,"message":"500: Internal Server Error"}"#
+            )))
+            .await,
+            transient
+        );
+        assert_eq!(
+            classify(Some(("503 Service Unavailable", "{}"))).await,
+            transient
+        );
+        assert_eq!(classify(None).await, transient);
+        assert_eq!(
+            classify(Some((
+                "404 Not Found",
+                r#"{"code":10062,"message":"Unknown interaction"}"#
+            )))
+            .await,
+            ErrorClass::UnknownInteraction
+        );
+        assert_eq!(
+            classify(Some((
+                "400 Bad Request",
+                r#"{"code":40060,"message":"Interaction has already been acknowledged."}"#
+            )))
+            .await,
+            ErrorClass::AlreadyAcknowledged
+        );
+        assert_eq!(
+            classify(Some((
+                "403 Forbidden",
+                r#"{"code":50013,"message":"Missing Permissions"}"#
+            )))
+            .await,
+            ErrorClass::Permanent
+        );
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let policy = RetryPolicy::default();
+        let delays: Vec<_> = (0..=4).map(|attempt| policy.backoff(attempt)).collect();
+        assert_eq!(
+            delays,
+            [250, 250, 500, 1000, 2000].map(Duration::from_millis)
+        );
+        assert_eq!(policy.backoff(7), policy.max_delay);
+        assert_eq!(policy.backoff(u32::MAX), policy.max_delay);
+    }
+
+    #[test]
+    fn deadlines_from_interaction_id() {
+        let created_ms = 1_700_000_000_000;
+        let id = Id::new(((created_ms - DISCORD_EPOCH_MS) << 22) | 0x3f_ffff);
+        let created_at = UNIX_EPOCH + Duration::from_millis(created_ms);
+        assert_eq!(interaction_created_at(id), created_at);
+        assert_eq!(response_deadline(id), created_at + RESPONSE_DEADLINE);
+        assert_eq!(token_deadline(id), created_at + TOKEN_DEADLINE);
+    }
+
+    /// Attempts made to send a request that always fails transiently.
+    async fn attempts(policy: RetryPolicy, deadline: SystemTime) -> u32 {
+        let client = client(Some((
+            "500 Internal Server Error",
+            r#"{"code":0,"message":""}"#,
+        )));
+        let attempts = AtomicU32::new(0);
+        let result = policy
+            .retry(deadline, || {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                client.gateway().into_future()
+            })
+            .await;
+        assert!(result.is_err());
+        attempts.into_inner()
+    }
+
+    #[tokio::test]
+    async fn retry_stops_at_the_deadline() {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(1),
+            ..RetryPolicy::default()
+        };
+        let far = SystemTime::now() + Duration::from_mins(1);
+        assert_eq!(attempts(policy, far).await, policy.max_attempts);
+
+        // Waiting out even the first backoff would pass the deadline
+        let policy = RetryPolicy {
+            base_delay: Duration::from_secs(5),
+            ..RetryPolicy::default()
+        };
+        let near = SystemTime::now() + Duration::from_secs(1);
+        assert_eq!(attempts(policy, near).await, 1);
+    }
+}
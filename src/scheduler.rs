@@ -0,0 +1,505 @@
+use crate::commands::TwilightError;
+use crate::context::State;
+use crate::retry::{ErrorClass, RetryPolicy};
+use crate::storage::{Storage, StorageError};
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use futures::FutureExt;
+use futures::future::BoxFuture;
+use rusqlite::{OptionalExtension, Transaction, TransactionBehavior};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::fmt::{Debug, Display, Formatter};
+use std::panic::AssertUnwindSafe;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::Notify;
+use tokio::task::JoinSet;
+use tracing::{debug, error, info, instrument, warn};
+
+/// Longest time the scheduler sleeps without checking storage for new jobs.
+const MAX_IDLE: Duration = Duration::from_mins(1);
+/// Retries of one-shot jobs that failed transiently.
+const JOB_RETRY_POLICY: RetryPolicy = RetryPolicy {
+    max_attempts: 5,
+    base_delay: Duration::from_mins(1),
+    max_delay: Duration::from_hours(1),
+};
+
+type JobFuture = BoxFuture<'static, Result<(), JobError>>;
+type Task = Arc<dyn Fn(Arc<State>) -> JobFuture + Send + Sync>;
+type Handler = Arc<dyn Fn(Arc<State>, &str) -> Result<JobFuture, serde_json::Error> + Send + Sync>;
+
+#[derive(Debug, Error)]
+pub enum SchedulerError {
+    #[error("No job handler registered for `{0}`")]
+    UnknownKind(&'static str),
+    #[error("Error storing the job: {0}")]
+    Storage(#[from] StorageError),
+}
+
+/// Error of a failed job, one-shot jobs failing transiently are retried later.
+#[derive(Debug)]
+pub struct JobError {
+    source: Box<dyn std::error::Error + Send + Sync>,
+    class: ErrorClass,
+}
+
+impl JobError {
+    /// An error that would happen the same way when running the job again.
+    pub fn permanent(source: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        JobError {
+            source: source.into(),
+            class: ErrorClass::Permanent,
+        }
+    }
+}
+
+impl Display for JobError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.source, f)
+    }
+}
+
+impl std::error::Error for JobError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+impl From<TwilightError> for JobError {
+    fn from(error: TwilightError) -> Self {
+        let class = match &error {
+            TwilightError::Http(error) => ErrorClass::classify(error),
+            TwilightError::Model(_) => ErrorClass::Permanent,
+        };
+        JobError {
+            source: error.into(),
+            class,
+        }
+    }
+}
+
+impl From<Infallible> for JobError {
+    fn from(never: Infallible) -> Self {
+        match never {}
+    }
+}
+
+/// Id of a persisted one-shot job.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct JobId(pub i64);
+
+struct PeriodicJob {
+    name: &'static str,
+    schedule: Schedule,
+    task: Task,
+}
+
+/// Persisted one-shot job that is due.
+struct DueJob {
+    id: JobId,
+    kind: String,
+    payload: String,
+    /// Earlier attempts that failed transiently.
+    failed_attempts: u32,
+}
+
+/// Run of a persisted one-shot job.
+#[derive(Copy, Clone, Debug)]
+struct PersistedRun {
+    id: JobId,
+    /// Attempt this run is, starting at 1.
+    attempt: u32,
+}
+
+/// Outcome of a job run, with the persisted job it ran for.
+type JobOutcome = (Option<PersistedRun>, String, Result<(), JobError>);
+
+/// Runs periodic jobs on cron schedules and one-shot jobs at the time they are due.
+///
+/// One-shot jobs are persisted until they finished running, so they survive restarts.
+/// Those failing transiently are retried with backoff.
+pub struct Scheduler {
+    storage: Storage,
+    periodic: Vec<PeriodicJob>,
+    /// One-shot job handlers, by job kind.
+    handlers: HashMap<&'static str, Handler>,
+    /// Wakes the run loop when a job is added or the scheduler is stopped.
+    wake: Notify,
+    stopped: AtomicBool,
+}
+
+impl Debug for Scheduler {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Scheduler")
+            .field("storage", &self.storage)
+            .field(
+                "periodic",
+                &self.periodic.iter().map(|job| job.name).collect::<Vec<_>>(),
+            )
+            .field("handlers", &self.handlers.keys().collect::<Vec<_>>())
+            .field("stopped", &self.stopped)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Scheduler {
+    pub fn new(storage: Storage) -> Self {
+        Scheduler {
+            storage,
+            periodic: Vec::new(),
+            handlers: HashMap::new(),
+            wake: Notify::new(),
+            stopped: AtomicBool::new(false),
+        }
+    }
+
+    /// Runs the job on the cron schedule, which includes seconds, e.g. `0 */15 * * * *`.
+    pub fn cron<F, Fut, E>(
+        mut self,
+        name: &'static str,
+        expression: &str,
+        job: F,
+    ) -> Result<Self, cron::error::Error>
+    where
+        F: Fn(Arc<State>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Into<JobError>,
+    {
+        let schedule = Schedule::from_str(expression)?;
+        self.periodic.push(PeriodicJob {
+            name,
+            schedule,
+            task: Arc::new(move |state| {
+                job(state).map(|result| result.map_err(Into::into)).boxed()
+            }),
+        });
+        Ok(self)
+    }
+
+    /// Registers the handler of one-shot jobs of the kind, which receives the payload they were scheduled with.
+    pub fn one_shot<T, F, Fut, E>(mut self, kind: &'static str, job: F) -> Self
+    where
+        T: DeserializeOwned,
+        F: Fn(Arc<State>, T) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Into<JobError>,
+    {
+        self.handlers.insert(
+            kind,
+            Arc::new(move |state, payload| {
+                let payload = serde_json::from_str(payload)?;
+                Ok(job(state, payload)
+                    .map(|result| result.map_err(Into::into))
+                    .boxed())
+            }),
+        );
+        self
+    }
+
+    /// Persists a one-shot job of the kind, to run at `due_at` or as soon as possible if that has passed.
+    pub async fn schedule(
+        &self,
+        kind: &'static str,
+        payload: &impl Serialize,
+        due_at: DateTime<Utc>,
+    ) -> Result<JobId, SchedulerError> {
+        let Ok(id) = self
+            .schedule_with(kind, payload, due_at, |_, _| Ok(Ok::<_, Infallible>(())))
+            .await?;
+        Ok(id)
+    }
+
+    /// Like [`Scheduler::schedule`], also running `with` in the same transaction,
+    /// e.g. to store rows referencing the job that are deleted along with it.
+    ///
+    /// The job is only persisted if `with` succeeds, its error is returned otherwise.
+    #[instrument(level = "info", skip(self, payload, with))]
+    pub async fn schedule_with<E, F>(
+        &self,
+        kind: &'static str,
+        payload: &impl Serialize,
+        due_at: DateTime<Utc>,
+        with: F,
+    ) -> Result<Result<JobId, E>, SchedulerError>
+    where
+        F: FnOnce(&Transaction, JobId) -> Result<Result<(), E>, StorageError> + Send + 'static,
+        E: Send + 'static,
+    {
+        if !self.handlers.contains_key(kind) {
+            return Err(SchedulerError::UnknownKind(kind));
+        }
+
+        let payload = serde_json::to_string(payload).map_err(StorageError::from)?;
+        let result = self
+            .storage
+            .with_connection(move |connection| {
+                // Taking the write lock up front, so what `with` reads can't change before the commit
+                let transaction =
+                    connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+                transaction.execute(
+                    "INSERT INTO scheduled_jobs (kind, payload, due_at) VALUES (?1, ?2, ?3)",
+                    (kind, payload, due_at.timestamp_millis()),
+                )?;
+                let id = JobId(transaction.last_insert_rowid());
+                let result = with(&transaction, id)?;
+                if result.is_ok() {
+                    transaction.commit()?;
+                }
+                Ok(result.map(|()| id))
+            })
+            .await?;
+        if let Ok(id) = result {
+            info!(?id, "Scheduled job");
+            self.wake.notify_one();
+        }
+        Ok(result)
+    }
+
+    /// Removes a one-shot job that has not run yet, returning whether it existed.
+    ///
+    /// A job that is already running is not interrupted.
+    #[instrument(level = "info", skip(self))]
+    pub async fn cancel(&self, id: JobId) -> Result<bool, StorageError> {
+        self.delete(id).await
+    }
+
+    /// Stops starting jobs, making [`Scheduler::run`] return once the running jobs finished.
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Release);
+        self.wake.notify_one();
+    }
+
+    /// Runs jobs as they become due until stopped, then gives running jobs `drain_timeout` to finish.
+    ///
+    /// One-shot jobs cut off by the timeout stay persisted and run again after a restart.
+    #[instrument(level = "info", skip_all)]
+    pub async fn run(&self, state: &Arc<State>, drain_timeout: Duration) {
+        let mut next_runs: Vec<_> = self
+            .periodic
+            .iter()
+            .map(|job| job.schedule.upcoming(Utc).next())
+            .collect();
+        let mut running = JoinSet::new();
+        // Persisted jobs stay due while they run, so they must not be started again
+        let mut started = HashSet::new();
+
+        while !self.stopped.load(Ordering::Acquire) {
+            let now = Utc::now();
+            for (job, next_run) in self.periodic.iter().zip(&mut next_runs) {
+                if next_run.is_some_and(|next_run| next_run <= now) {
+                    debug!(job = job.name, "Starting periodic job");
+                    let run = (job.task)(state.clone());
+                    running.spawn(run_job(None, job.name.to_owned(), run));
+                    *next_run = job.schedule.after(&now).next();
+                }
+            }
+
+            let next_due = match self
+                .start_due_jobs(state, now, &mut running, &mut started)
+                .await
+            {
+                Ok(next_due) => next_due,
+                Err(error) => {
+                    error!(%error, "Could not load scheduled jobs");
+                    None
+                }
+            };
+
+            let idle =
+                next_runs
+                    .iter()
+                    .flatten()
+                    .chain(&next_due)
+                    .min()
+                    .map_or(MAX_IDLE, |wake_at| {
+                        (*wake_at - now)
+                            .to_std()
+                            .unwrap_or(Duration::ZERO)
+                            .min(MAX_IDLE)
+                    });
+            tokio::select! {
+                () = tokio::time::sleep(idle) => {}
+                () = self.wake.notified() => {}
+                Some(outcome) = running.join_next(), if !running.is_empty() => {
+                    self.finish_job(outcome, &mut started).await;
+                }
+            }
+        }
+
+        info!(running = running.len(), "Stopping scheduler");
+        let drain = async {
+            while let Some(outcome) = running.join_next().await {
+                self.finish_job(outcome, &mut started).await;
+            }
+        };
+        if tokio::time::timeout(drain_timeout, drain).await.is_err() {
+            warn!(
+                cancelled = running.len(),
+                "Scheduled jobs did not finish in time"
+            );
+        }
+    }
+
+    /// Starts the persisted jobs that are due, returning when the next one is due.
+    async fn start_due_jobs(
+        &self,
+        state: &Arc<State>,
+        now: DateTime<Utc>,
+        running: &mut JoinSet<JobOutcome>,
+        started: &mut HashSet<JobId>,
+    ) -> Result<Option<DateTime<Utc>>, StorageError> {
+        let now_millis = now.timestamp_millis();
+        let (due, next_due) = self
+            .storage
+            .with_connection(move |connection| {
+                let mut statement = connection.prepare(
+                    "SELECT id, kind, payload, attempts FROM scheduled_jobs WHERE due_at <= ?1 ORDER BY due_at",
+                )?;
+                let due = statement
+                    .query_map([now_millis], |row| {
+                        Ok(DueJob {
+                            id: JobId(row.get(0)?),
+                            kind: row.get(1)?,
+                            payload: row.get(2)?,
+                            failed_attempts: row.get(3)?,
+                        })
+                    })?
+                    .collect::<Result<Vec<_>, _>>()?;
+                let next_due: Option<i64> = connection
+                    .query_row(
+                        "SELECT MIN(due_at) FROM scheduled_jobs WHERE due_at > ?1",
+                        [now_millis],
+                        |row| row.get(0),
+                    )
+                    .optional()?
+                    .flatten();
+                Ok((due, next_due))
+            })
+            .await?;
+
+        for job in due {
+            if started.contains(&job.id) {
+                continue;
+            }
+
+            let run = self
+                .handlers
+                .get(job.kind.as_str())
+                .map(|handler| handler(state.clone(), &job.payload));
+            match run {
+                Some(Ok(run)) => {
+                    debug!(id = ?job.id, kind = job.kind, "Starting scheduled job");
+                    started.insert(job.id);
+                    let persisted = PersistedRun {
+                        id: job.id,
+                        attempt: job.failed_attempts.saturating_add(1),
+                    };
+                    running.spawn(run_job(Some(persisted), job.kind, run));
+                }
+                Some(Err(error)) => {
+                    error!(id = ?job.id, kind = job.kind, %error, "Dropping job with invalid payload");
+                    self.delete(job.id).await?;
+                }
+                None => {
+                    warn!(id = ?job.id, kind = job.kind, "Dropping job without handler");
+                    self.delete(job.id).await?;
+                }
+            }
+        }
+
+        Ok(next_due.and_then(DateTime::from_timestamp_millis))
+    }
+
+    /// Logs the outcome of the job, and if it was persisted, reschedules it after a transient
+    /// failure or removes it from storage.
+    async fn finish_job(
+        &self,
+        outcome: Result<JobOutcome, tokio::task::JoinError>,
+        started: &mut HashSet<JobId>,
+    ) {
+        let (persisted, name, result) = match outcome {
+            Ok(outcome) => outcome,
+            Err(error) => {
+                error!(%error, "Scheduled job task failed");
+                return;
+            }
+        };
+        let Some(PersistedRun { id, attempt }) = persisted else {
+            match result {
+                Ok(()) => debug!(job = name, "Finished job"),
+                Err(error) => error!(job = name, %error, "Job failed"),
+            }
+            return;
+        };
+        started.remove(&id);
+
+        let retry_in = match result {
+            Ok(()) => {
+                debug!(?id, job = name, "Finished job");
+                None
+            }
+            Err(JobError {
+                source,
+                class: ErrorClass::Transient { retry_after },
+            }) if attempt < JOB_RETRY_POLICY.max_attempts => {
+                let delay = JOB_RETRY_POLICY
+                    .backoff(attempt)
+                    .max(retry_after.unwrap_or_default());
+                warn!(?id, job = name, attempt, ?delay, error = %source, "Job failed, retrying later");
+                Some(delay)
+            }
+            Err(error) => {
+                error!(?id, job = name, attempt, %error, "Job failed");
+                None
+            }
+        };
+
+        let stored = match retry_in {
+            Some(delay) => self.reschedule(id, Utc::now() + delay).await,
+            None => self.delete(id).await.map(drop),
+        };
+        if let Err(error) = stored {
+            error!(?id, %error, "Could not update finished job");
+        }
+    }
+
+    /// Moves the job to `due_at`, counting the failed attempt.
+    async fn reschedule(&self, id: JobId, due_at: DateTime<Utc>) -> Result<(), StorageError> {
+        self.storage
+            .with_connection(move |connection| {
+                connection.execute(
+                    "UPDATE scheduled_jobs SET due_at = ?2, attempts = attempts + 1 WHERE id = ?1",
+                    (id.0, due_at.timestamp_millis()),
+                )?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// Deletes the job, returning whether it existed.
+    async fn delete(&self, id: JobId) -> Result<bool, StorageError> {
+        self.storage
+            .with_connection(move |connection| {
+                let deleted =
+                    connection.execute("DELETE FROM scheduled_jobs WHERE id = ?1", [id.0])?;
+                Ok(deleted > 0)
+            })
+            .await
+    }
+}
+
+/// Runs the job, turning panics into errors so persisted jobs are still cleaned up.
+async fn run_job(persisted: Option<PersistedRun>, name: String, run: JobFuture) -> JobOutcome {
+    let result = AssertUnwindSafe(run)
+        .catch_unwind()
+        .await
+        .unwrap_or_else(|_| Err(JobError::permanent("job panicked")));
+    (persisted, name, result)
+}
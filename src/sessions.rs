@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::path::Path;
+use thiserror::Error;
+use tracing::{debug, instrument};
+use twilight_gateway::{ConfigBuilder, Session, Shard, ShardId};
+
+#[derive(Debug, Error)]
+pub enum SessionStoreError {
+    #[error("Io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Json error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Gateway session of a shard that can be resumed after a restart.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SavedSession {
+    pub total: u32,
+    pub session: Session,
+    pub resume_url: Option<String>,
+}
+
+impl SavedSession {
+    /// Captures the session of a shard, if it has one.
+    pub fn from_shard<Q>(shard: &Shard<Q>) -> Option<Self> {
+        shard.session().map(|session| SavedSession {
+            total: shard.id().total(),
+            session: session.clone(),
+            resume_url: shard.resume_url().map(str::to_string),
+        })
+    }
+}
+
+/// Gateway sessions by shard number, persisted between process restarts.
+#[derive(Clone, Eq, PartialEq, Default, Debug, Serialize, Deserialize)]
+pub struct SessionStore {
+    sessions: BTreeMap<u32, SavedSession>,
+}
+
+impl SessionStore {
+    /// Loads the sessions from `path` and removes the file, so the sessions are not resumed twice.
+    ///
+    /// A missing file results in an empty store.
+    #[instrument(level = "debug")]
+    pub fn take_from_file(path: &Path) -> Result<Self, SessionStoreError> {
+        let contents = match std::fs::read(path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                debug!("No session file present");
+                return Ok(SessionStore::default());
+            }
+            Err(error) => return Err(error.into()),
+        };
+        std::fs::remove_file(path)?;
+
+        Ok(serde_json::from_slice(&contents)?)
+    }
+
+    #[instrument(level = "debug")]
+    pub fn save_to_file(&self, path: &Path) -> Result<(), SessionStoreError> {
+        std::fs::write(path, serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+
+    pub fn insert(&mut self, shard_id: ShardId, session: SavedSession) {
+        self.sessions.insert(shard_id.number(), session);
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Configures the shard to resume its saved session, if the shard layout did not change since.
+    pub fn configure_shard<Q>(
+        &self,
+        shard_id: ShardId,
+        mut builder: ConfigBuilder<Q>,
+    ) -> ConfigBuilder<Q> {
+        let Some(saved) = self.sessions.get(&shard_id.number()) else {
+            return builder;
+        };
+        if saved.total != shard_id.total() {
+            debug!(shard.id = %shard_id, saved.total, "Shard total changed, not resuming");
+            return builder;
+        }
+
+        builder = builder.session(saved.session.clone());
+        if let Some(resume_url) = &saved.resume_url {
+            builder = builder.resume_url(resume_url.clone());
+        }
+        builder
+    }
+}
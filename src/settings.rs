@@ -0,0 +1,394 @@
+use crate::storage::{Storage, StorageError};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+use std::sync::{PoisonError, RwLock};
+use thiserror::Error;
+use tracing::instrument;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, GuildMarker};
+
+/// Record key the settings are stored under.
+const SETTINGS_KEY: &str = "settings";
+/// Longest cooldown a guild can configure, in seconds.
+pub const MAX_COOLDOWN_SECS: u64 = 24 * 60 * 60;
+
+/// Reason a setting could not be changed, shown to the user.
+#[derive(Clone, Eq, PartialEq, Debug, Error)]
+pub enum SettingError {
+    #[error("There is no setting called `{0}`.")]
+    UnknownKey(String),
+    #[error("`{0}` is not a channel, use a channel mention or id.")]
+    InvalidChannel(String),
+    #[error("There is no command called `{0}`.")]
+    UnknownCommand(String),
+    #[error(
+        "`{0}` is not a cooldown, use `command=seconds` with at most {MAX_COOLDOWN_SECS} seconds."
+    )]
+    InvalidCooldown(String),
+}
+
+/// Guild settings kept in memory once loaded, changed through it so the memory stays current.
+#[derive(Debug)]
+pub struct SettingsCache {
+    storage: Storage,
+    cached: RwLock<CachedSettings>,
+}
+
+#[derive(Default, Debug)]
+struct CachedSettings {
+    /// Incremented on every update, so loads started before it don't cache outdated settings.
+    generation: u64,
+    guilds: HashMap<Id<GuildMarker>, GuildSettings>,
+}
+
+impl SettingsCache {
+    pub fn new(storage: Storage) -> Self {
+        SettingsCache {
+            storage,
+            cached: RwLock::default(),
+        }
+    }
+
+    /// The guild's settings, or the defaults if it never changed them.
+    #[instrument(level = "trace", skip(self))]
+    pub async fn get(&self, guild_id: Id<GuildMarker>) -> Result<GuildSettings, StorageError> {
+        let generation = {
+            let cached = self.cached.read().unwrap_or_else(PoisonError::into_inner);
+            if let Some(settings) = cached.guilds.get(&guild_id) {
+                return Ok(settings.clone());
+            }
+            cached.generation
+        };
+
+        let settings = GuildSettings::load(&self.storage, guild_id).await?;
+        let mut cached = self.cached.write().unwrap_or_else(PoisonError::into_inner);
+        if cached.generation == generation {
+            cached.guilds.insert(guild_id, settings.clone());
+        }
+        Ok(settings)
+    }
+
+    /// Changes the guild's settings in one transaction, saving them only if `update` succeeds.
+    #[instrument(level = "debug", skip(self, update))]
+    pub async fn update<R, E>(
+        &self,
+        guild_id: Id<GuildMarker>,
+        update: impl FnOnce(&mut GuildSettings) -> Result<R, E> + Send + 'static,
+    ) -> Result<Result<R, E>, StorageError>
+    where
+        R: Send + 'static,
+        E: Send + 'static,
+    {
+        let result = GuildSettings::update(&self.storage, guild_id, update).await;
+        let mut cached = self.cached.write().unwrap_or_else(PoisonError::into_inner);
+        cached.generation += 1;
+        cached.guilds.remove(&guild_id);
+        result
+    }
+}
+
+/// Settings a guild can change with `/config`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum SettingKey {
+    LogChannel,
+    DisabledCommands,
+    Cooldowns,
+}
+
+impl SettingKey {
+    pub const ALL: [SettingKey; 3] = [
+        SettingKey::LogChannel,
+        SettingKey::DisabledCommands,
+        SettingKey::Cooldowns,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SettingKey::LogChannel => "log-channel",
+            SettingKey::DisabledCommands => "disabled-commands",
+            SettingKey::Cooldowns => "cooldowns",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            SettingKey::LogChannel => "Channel changes to the settings are logged to",
+            SettingKey::DisabledCommands => "Comma separated commands that can't be used",
+            SettingKey::Cooldowns => "Comma separated command=seconds cooldowns",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self, SettingError> {
+        SettingKey::ALL
+            .into_iter()
+            .find(|key| key.name() == name)
+            .ok_or_else(|| SettingError::UnknownKey(name.to_owned()))
+    }
+}
+
+/// Per guild settings, stored persistently and inserted into the request extensions.
+#[derive(Clone, Default, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuildSettings {
+    pub log_channel_id: Option<Id<ChannelMarker>>,
+    pub disabled_commands: BTreeSet<String>,
+    /// Cooldown per user in seconds, by command name.
+    pub cooldowns: BTreeMap<String, u64>,
+}
+
+impl GuildSettings {
+    /// The guild's settings, or the defaults if it never changed them.
+    async fn load(storage: &Storage, guild_id: Id<GuildMarker>) -> Result<Self, StorageError> {
+        let settings = storage.guilds().get(guild_id, SETTINGS_KEY).await?;
+        Ok(settings.unwrap_or_default())
+    }
+
+    /// Changes the guild's settings in one transaction, saving them only if `update` succeeds.
+    async fn update<R, E>(
+        storage: &Storage,
+        guild_id: Id<GuildMarker>,
+        update: impl FnOnce(&mut GuildSettings) -> Result<R, E> + Send + 'static,
+    ) -> Result<Result<R, E>, StorageError>
+    where
+        R: Send + 'static,
+        E: Send + 'static,
+    {
+        storage
+            .guilds()
+            .update(guild_id, SETTINGS_KEY, update)
+            .await
+    }
+
+    /// The value of the setting, formatted for display.
+    pub fn get(&self, key: SettingKey) -> String {
+        fn list(items: impl IntoIterator<Item = String>) -> String {
+            let list = items
+                .into_iter()
+                .map(|item| format!("`{item}`"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            if list.is_empty() {
+                "none".to_owned()
+            } else {
+                list
+            }
+        }
+
+        match key {
+            SettingKey::LogChannel => self.log_channel_id.map_or_else(
+                || "none".to_owned(),
+                |channel_id| format!("<#{channel_id}>"),
+            ),
+            SettingKey::DisabledCommands => list(self.disabled_commands.iter().cloned()),
+            SettingKey::Cooldowns => list(
+                self.cooldowns
+                    .iter()
+                    .map(|(command, seconds)| format!("{command}={seconds}")),
+            ),
+        }
+    }
+
+    /// All settings, one per line.
+    pub fn describe(&self) -> String {
+        let mut description = String::new();
+        for key in SettingKey::ALL {
+            _ = writeln!(description, "**{}**: {}", key.name(), self.get(key));
+        }
+        description
+    }
+
+    /// Parses and validates the value, then changes the setting.
+    pub fn set(
+        &mut self,
+        key: SettingKey,
+        value: &str,
+        is_command: impl Fn(&str) -> bool,
+    ) -> Result<(), SettingError> {
+        let items = || {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+        };
+        match key {
+            SettingKey::LogChannel => {
+                let id = value.trim().trim_start_matches("<#").trim_end_matches('>');
+                let channel_id = id
+                    .parse()
+                    .map_err(|_| SettingError::InvalidChannel(value.to_owned()))?;
+                self.log_channel_id = Some(channel_id);
+            }
+            SettingKey::DisabledCommands => {
+                let commands = items()
+                    .map(|command| {
+                        let command = command.trim_start_matches('/');
+                        if is_command(command) {
+                            Ok(command.to_owned())
+                        } else {
+                            Err(SettingError::UnknownCommand(command.to_owned()))
+                        }
+                    })
+                    .collect::<Result<_, _>>()?;
+                self.disabled_commands = commands;
+            }
+            SettingKey::Cooldowns => {
+                let cooldowns = items()
+                    .map(|item| {
+                        let invalid = || SettingError::InvalidCooldown(item.to_owned());
+                        let (command, seconds) = item.split_once('=').ok_or_else(invalid)?;
+                        let command = command.trim().trim_start_matches('/');
+                        let seconds: u64 = seconds.trim().parse().map_err(|_| invalid())?;
+                        if seconds > MAX_COOLDOWN_SECS {
+                            return Err(invalid());
+                        }
+                        if !is_command(command) {
+                            return Err(SettingError::UnknownCommand(command.to_owned()));
+                        }
+                        Ok((command.to_owned(), seconds))
+                    })
+                    .collect::<Result<_, _>>()?;
+                self.cooldowns = cooldowns;
+            }
+        }
+        Ok(())
+    }
+
+    /// Changes the setting back to its default.
+    pub fn reset(&mut self, key: SettingKey) {
+        let defaults = GuildSettings::default();
+        match key {
+            SettingKey::LogChannel => self.log_channel_id = defaults.log_channel_id,
+            SettingKey::DisabledCommands => self.disabled_commands = defaults.disabled_commands,
+            SettingKey::Cooldowns => self.cooldowns = defaults.cooldowns,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_command(name: &str) -> bool {
+        matches!(name, "counter" | "remind")
+    }
+
+    fn set(key: SettingKey, value: &str) -> Result<GuildSettings, SettingError> {
+        let mut settings = GuildSettings::default();
+        settings.set(key, value, is_command)?;
+        Ok(settings)
+    }
+
+    #[test]
+    fn log_channel() {
+        let expected = Some(Id::new(123));
+        assert_eq!(
+            set(SettingKey::LogChannel, "<#123>")
+                .unwrap()
+                .log_channel_id,
+            expected
+        );
+        assert_eq!(
+            set(SettingKey::LogChannel, " 123 ").unwrap().log_channel_id,
+            expected
+        );
+        assert_eq!(
+            set(SettingKey::LogChannel, "general"),
+            Err(SettingError::InvalidChannel("general".to_owned()))
+        );
+        assert!(set(SettingKey::LogChannel, "0").is_err());
+    }
+
+    #[test]
+    fn disabled_commands() {
+        let settings = set(SettingKey::DisabledCommands, "/counter, remind,,").unwrap();
+        assert_eq!(
+            settings.disabled_commands,
+            BTreeSet::from(["counter".to_owned(), "remind".to_owned()])
+        );
+        assert!(
+            set(SettingKey::DisabledCommands, "")
+                .unwrap()
+                .disabled_commands
+                .is_empty()
+        );
+        assert_eq!(
+            set(SettingKey::DisabledCommands, "counter, ban"),
+            Err(SettingError::UnknownCommand("ban".to_owned()))
+        );
+    }
+
+    #[test]
+    fn cooldowns() {
+        let settings = set(SettingKey::Cooldowns, "counter=5, /remind = 60").unwrap();
+        assert_eq!(
+            settings.cooldowns,
+            BTreeMap::from([("counter".to_owned(), 5), ("remind".to_owned(), 60)])
+        );
+        assert!(
+            set(
+                SettingKey::Cooldowns,
+                &format!("counter={MAX_COOLDOWN_SECS}")
+            )
+            .is_ok()
+        );
+        assert_eq!(
+            set(
+                SettingKey::Cooldowns,
+                &format!("counter={}", MAX_COOLDOWN_SECS + 1)
+            ),
+            Err(SettingError::InvalidCooldown(format!(
+                "counter={}",
+                MAX_COOLDOWN_SECS + 1
+            )))
+        );
+        assert_eq!(
+            set(SettingKey::Cooldowns, "counter"),
+            Err(SettingError::InvalidCooldown("counter".to_owned()))
+        );
+        assert_eq!(
+            set(SettingKey::Cooldowns, "counter=-1"),
+            Err(SettingError::InvalidCooldown("counter=-1".to_owned()))
+        );
+        assert_eq!(
+            set(SettingKey::Cooldowns, "ban=5"),
+            Err(SettingError::UnknownCommand("ban".to_owned()))
+        );
+    }
+
+    #[test]
+    fn invalid_value_keeps_setting() {
+        let mut settings = set(SettingKey::DisabledCommands, "counter").unwrap();
+        let before = settings.clone();
+        assert!(
+            settings
+                .set(SettingKey::DisabledCommands, "counter, ban", is_command)
+                .is_err()
+        );
+        assert_eq!(settings, before);
+    }
+
+    #[tokio::test]
+    async fn cache_sees_updates() {
+        let storage = Storage::open(std::path::Path::new(":memory:"), 1).unwrap();
+        let cache = SettingsCache::new(storage);
+        let guild_id = Id::new(1);
+        assert_eq!(cache.get(guild_id).await.unwrap(), GuildSettings::default());
+
+        let updated = cache
+            .update(guild_id, |settings| {
+                settings.set(SettingKey::DisabledCommands, "counter", is_command)
+            })
+            .await
+            .unwrap();
+        assert!(updated.is_ok());
+        assert_eq!(
+            cache.get(guild_id).await.unwrap().disabled_commands,
+            BTreeSet::from(["counter".to_owned()])
+        );
+        assert_eq!(
+            cache.get(Id::new(2)).await.unwrap(),
+            GuildSettings::default()
+        );
+    }
+}
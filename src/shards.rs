@@ -0,0 +1,855 @@
+use crate::cache::Cache;
+use crate::config::ShardLayoutError;
+use crate::context::{ContextFactory, ReceivedAt, State};
+use crate::events::Events;
+use crate::framework::{EventRunner, panic_message};
+use crate::hooks::{BlocklistVeto, MaintenanceVeto};
+use crate::metrics::Metrics;
+use crate::queue::IdentifyQueue;
+use crate::retry::{self, ErrorClass};
+use crate::sessions::{SavedSession, SessionStore};
+use futures::FutureExt;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::{Future, IntoFuture};
+use std::ops::{ControlFlow, Range};
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, PoisonError};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::{AbortHandle, JoinError, JoinSet};
+use tokio::time::{Instant, MissedTickBehavior};
+use tower::{Service, ServiceExt};
+use tracing::{Instrument, debug, error, info, info_span, instrument, warn};
+use twilight_gateway::error::ReceiveMessageError;
+use twilight_gateway::{
+    CloseFrame, Config, EventTypeFlags, MessageSender, Shard, ShardId, StreamExt as _,
+    create_iterator,
+};
+use twilight_model::application::interaction::{Interaction, InteractionData, InteractionType};
+use twilight_model::channel::message::MessageFlags;
+use twilight_model::gateway::event::Event;
+use twilight_model::http::interaction::{InteractionResponse, InteractionResponseType};
+use twilight_model::id::Id;
+use twilight_model::id::marker::{GuildMarker, InteractionMarker, UserMarker};
+use twilight_util::builder::InteractionResponseDataBuilder;
+
+/// Time a single shard is given to identify when waiting for a new generation of shards.
+const IDENTIFY_INTERVAL: Duration = Duration::from_secs(5);
+/// Extra time given to a new generation of shards to become ready, on top of identifying.
+const RESHARD_GRACE_PERIOD: Duration = Duration::from_mins(1);
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum ShardExit {
+    /// The bot is shutting down, the session can be resumed later if present.
+    Shutdown(Option<SavedSession>),
+    /// The shard was replaced by a newer generation of shards.
+    Retired,
+    /// The shard stream ended, most likely because the shard was fatally closed.
+    StreamEnded,
+}
+
+#[derive(Clone, Debug)]
+pub struct RunnerHandle {
+    pub generation: u64,
+    active_generation: Arc<AtomicU64>,
+    ready_sender: mpsc::UnboundedSender<(u64, ShardId)>,
+}
+
+impl RunnerHandle {
+    /// Whether events received by this shard should be processed.
+    pub fn is_active(&self) -> bool {
+        self.active_generation.load(Ordering::Acquire) == self.generation
+    }
+
+    /// Whether this shard has been replaced by a newer generation of shards.
+    pub fn is_retired(&self) -> bool {
+        self.active_generation.load(Ordering::Acquire) > self.generation
+    }
+
+    fn notify_ready(&self, shard_id: ShardId) {
+        // The supervisor not listening anymore just means nobody is waiting for this shard
+        _ = self.ready_sender.send((self.generation, shard_id));
+    }
+}
+
+#[instrument(
+    level = "info",
+    fields(shard.id = %shard.id(), shard.generation = handle.generation),
+    skip(router, shard, handle),
+)]
+pub async fn shard_runner(
+    router: impl Service<
+        (ContextFactory, Interaction),
+        Response = (),
+        Error = (),
+        Future = impl Future<Output = Result<(), ()>> + Send,
+    > + Clone
+    + Send
+    + 'static,
+    context_factory: ContextFactory,
+    mut shard: Shard<IdentifyQueue>,
+    handle: RunnerHandle,
+) -> ShardExit {
+    let cache = context_factory.state.cache();
+    let event_types = EventTypeFlags::INTERACTION_CREATE
+        | EventTypeFlags::READY
+        | <Events as EventRunner<ContextFactory>>::EVENT_TYPES
+        | cache.map_or(EventTypeFlags::empty(), Cache::event_types);
+    while let Some(event) = shard.next_event(event_types).await {
+        if let Ok(Event::Ready(_)) = &event {
+            handle.notify_ready(shard.id());
+        }
+        // Handlers see the cache as of the event they are handling
+        if let (Some(cache), Ok(event)) = (cache, &event) {
+            cache.update(event);
+        }
+
+        if let ControlFlow::Break(exit) =
+            handle_event(router.clone(), context_factory.clone(), &handle, event).await
+        {
+            return match exit {
+                ShardExit::Shutdown(_) => ShardExit::Shutdown(SavedSession::from_shard(&shard)),
+                exit => exit,
+            };
+        }
+    }
+
+    ShardExit::StreamEnded
+}
+
+#[instrument(level = "debug", skip(router, handle))]
+async fn handle_event(
+    mut router: impl Service<
+        (ContextFactory, Interaction),
+        Response = (),
+        Error = (),
+        Future = impl Future<Output = Result<(), ()>> + Send,
+    > + Clone
+    + Send
+    + 'static,
+    mut context_factory: ContextFactory,
+    handle: &RunnerHandle,
+    event: Result<Event, ReceiveMessageError>,
+) -> ControlFlow<ShardExit> {
+    fn assert_fully_processed<Fut: Future<Output = Result<(), ()>>>(it: Fut) -> Fut {
+        it
+    }
+
+    let interaction = match event {
+        Ok(Event::GatewayClose(close_frame))
+            if context_factory.state.shutdown.load(Ordering::Acquire) =>
+        {
+            // TODO: Some kind of timeout for shutdown
+            debug!(?close_frame, "GatewayClose after shutdown");
+            // The session is captured by the shard runner, which owns the shard
+            return ControlFlow::Break(ShardExit::Shutdown(None));
+        }
+        Ok(Event::GatewayClose(close_frame)) if handle.is_retired() => {
+            debug!(?close_frame, "GatewayClose after retirement");
+            return ControlFlow::Break(ShardExit::Retired);
+        }
+        Err(error) => {
+            warn!(%error, "Error receiving gateway event");
+            return ControlFlow::Continue(());
+        }
+        // Shards of a generation that is not active yet only connect, the old shards still handle everything
+        Ok(_) if !handle.is_active() => return ControlFlow::Continue(()),
+        Ok(Event::InteractionCreate(interaction_create)) => interaction_create.0,
+        // Events only received for the cache have no handlers to run
+        Ok(event)
+            if !<Events as EventRunner<ContextFactory>>::EVENT_TYPES
+                .intersects(EventTypeFlags::from(event.kind())) =>
+        {
+            return ControlFlow::Continue(());
+        }
+        Ok(event) => {
+            tokio::spawn(
+                async move {
+                    if let Err(errors) = Events::run(context_factory, event).await {
+                        for error in errors {
+                            error!(%error);
+                        }
+                    }
+                }
+                .instrument(info_span!("event runner execution")),
+            );
+            return ControlFlow::Continue(());
+        }
+    };
+
+    // Handlers waiting for a component get it directly, they continue with their command's capacity.
+    // Vetoed presses go through the router instead, where the vetoes reply to them.
+    let vetoed = BlocklistVeto::applies(&context_factory, &interaction)
+        || MaintenanceVeto::applies(&context_factory, &interaction);
+    let interaction = if vetoed {
+        interaction
+    } else {
+        let Some(interaction) = context_factory.state.collectors.deliver(interaction) else {
+            return ControlFlow::Continue(());
+        };
+        interaction
+    };
+
+    context_factory
+        .extensions
+        .insert(ReceivedAt(std::time::Instant::now()));
+    let state = context_factory.state.clone();
+    let summary = InteractionSummary::new(&interaction);
+    let Some(reservation) = state.executor.try_reserve(interaction.guild_id) else {
+        tokio::spawn(
+            async move {
+                reply_ephemeral(
+                    &state,
+                    &summary,
+                    "The bot is busy right now, please try again in a moment.",
+                )
+                .await;
+            }
+            .instrument(info_span!("busy reply")),
+        );
+        return ControlFlow::Continue(());
+    };
+
+    context_factory.extensions.insert(reservation.capacity());
+    // TODO: Commands probably need to be abortable? Right now they'd be just cut off when the application exits
+    reservation.spawn(assert_fully_processed(
+        async move {
+            if let Some(guild_id) = interaction.guild_id {
+                match state.guild_settings().get(guild_id).await {
+                    Ok(settings) => _ = context_factory.extensions.insert(settings),
+                    Err(error) => warn!(%error, "Could not load guild settings, using defaults"),
+                }
+            }
+            let execution = AssertUnwindSafe(async move {
+                router
+                    .ready()
+                    .await?
+                    .call((context_factory, interaction))
+                    .await
+            });
+            match execution.catch_unwind().await {
+                Ok(result) => result,
+                Err(panic) => {
+                    report_panic(&state, &summary, panic_message(panic.as_ref())).await;
+                    Err(())
+                }
+            }
+        }
+        .instrument(info_span!("command service execution")),
+    ));
+
+    ControlFlow::Continue(())
+}
+
+/// Details of an interaction that are still needed after the interaction was handed to the router.
+#[derive(Clone, Debug)]
+struct InteractionSummary {
+    id: Id<InteractionMarker>,
+    token: String,
+    kind: InteractionType,
+    name: Option<String>,
+    user_id: Option<Id<UserMarker>>,
+    guild_id: Option<Id<GuildMarker>>,
+}
+
+impl InteractionSummary {
+    fn new(interaction: &Interaction) -> Self {
+        let name = match &interaction.data {
+            Some(InteractionData::ApplicationCommand(data)) => Some(data.name.clone()),
+            Some(InteractionData::MessageComponent(data)) => Some(data.custom_id.clone()),
+            Some(InteractionData::ModalSubmit(data)) => Some(data.custom_id.clone()),
+            _ => None,
+        };
+
+        InteractionSummary {
+            id: interaction.id,
+            token: interaction.token.clone(),
+            kind: interaction.kind,
+            name,
+            user_id: interaction.author_id(),
+            guild_id: interaction.guild_id,
+        }
+    }
+}
+
+#[instrument(level = "debug", skip(state))]
+async fn report_panic(state: &State, summary: &InteractionSummary, message: &str) {
+    error!(
+        command = summary.name,
+        user.id = ?summary.user_id,
+        guild.id = ?summary.guild_id,
+        panic = message,
+        "Command panicked",
+    );
+    Metrics::increment(&state.metrics.command_panics);
+
+    reply_ephemeral(
+        state,
+        summary,
+        "Something went wrong while running this command.",
+    )
+    .await;
+}
+
+/// Replies with an ephemeral message, or sends a followup if the interaction was already answered.
+#[instrument(level = "debug", skip(state))]
+async fn reply_ephemeral(state: &State, summary: &InteractionSummary, content: &str) {
+    // Other interaction kinds can not be answered with a message
+    if !matches!(
+        summary.kind,
+        InteractionType::ApplicationCommand
+            | InteractionType::MessageComponent
+            | InteractionType::ModalSubmit
+    ) {
+        return;
+    }
+
+    let client = state.interaction_client();
+    let response = InteractionResponse {
+        kind: InteractionResponseType::ChannelMessageWithSource,
+        data: Some(
+            InteractionResponseDataBuilder::new()
+                .content(content)
+                .flags(MessageFlags::EPHEMERAL)
+                .build(),
+        ),
+    };
+    let Err(error) = state
+        .retry_policy
+        .respond(retry::response_deadline(summary.id), || {
+            client
+                .create_response(summary.id, &summary.token, &response)
+                .into_future()
+        })
+        .await
+    else {
+        return;
+    };
+    if ErrorClass::classify(&error) != ErrorClass::AlreadyAcknowledged {
+        warn!(%error, "Could not send ephemeral reply");
+        return;
+    }
+    debug!("Interaction was already answered, sending followup instead");
+
+    if let Err(error) = state
+        .retry_policy
+        .retry(retry::token_deadline(summary.id), || {
+            client
+                .create_followup(&summary.token)
+                .content(content)
+                .flags(MessageFlags::EPHEMERAL)
+                .into_future()
+        })
+        .await
+    {
+        warn!(%error, "Could not send ephemeral followup");
+    }
+}
+
+/// Which shards are run by this process.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct ShardLayout {
+    /// Fixed total shard count, or `None` to follow Discord's recommendation.
+    pub total: Option<u32>,
+    pub selection: ShardSelection,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ShardSelection {
+    All,
+    /// Shards from `start` up to, but not including, `end`.
+    Range {
+        start: u32,
+        end: u32,
+    },
+    /// The `index`th of `count` evenly sized chunks of shards.
+    Cluster {
+        index: u32,
+        count: u32,
+    },
+}
+
+impl ShardLayout {
+    pub fn total(&self, recommended: u32) -> u32 {
+        self.total.unwrap_or(recommended)
+    }
+
+    /// Shard numbers run by this process for the given total.
+    pub fn shards(&self, total: u32) -> Range<u32> {
+        match self.selection {
+            ShardSelection::All => 0..total,
+            ShardSelection::Range { start, end } => start.min(total)..end.min(total),
+            ShardSelection::Cluster { index, count } => {
+                let per_cluster = total.div_ceil(count);
+                let start = index.saturating_mul(per_cluster).min(total);
+                start..start.saturating_add(per_cluster).min(total)
+            }
+        }
+    }
+
+    /// Like [`ShardLayout::shards`], but rejects a selection that includes none of the shards.
+    pub fn non_empty_shards(&self, total: u32) -> Result<Range<u32>, ShardLayoutError> {
+        let range = self.shards(total);
+        if range.is_empty() {
+            return Err(ShardLayoutError::NoShards { total });
+        }
+        Ok(range)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SupervisorConfig {
+    /// Delay before restarting a shard that exited for the first time.
+    pub restart_backoff_min: Duration,
+    /// Upper bound for the delay before restarting a repeatedly failing shard.
+    pub restart_backoff_max: Duration,
+    /// How often the recommended shard count is checked.
+    pub reshard_interval: Duration,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        SupervisorConfig {
+            restart_backoff_min: Duration::from_secs(1),
+            restart_backoff_max: Duration::from_mins(5),
+            reshard_interval: Duration::from_hours(1),
+        }
+    }
+}
+
+impl SupervisorConfig {
+    fn restart_backoff(&self, failures: u32) -> Duration {
+        self.restart_backoff_min
+            .saturating_mul(2_u32.saturating_pow(failures))
+            .min(self.restart_backoff_max)
+    }
+}
+
+#[derive(Debug)]
+struct RunnerInfo {
+    shard_id: ShardId,
+    generation: u64,
+    started: Instant,
+    /// Number of consecutive failures before this run.
+    failures: u32,
+    abort_handle: AbortHandle,
+}
+
+#[derive(Debug)]
+struct PendingGeneration {
+    generation: u64,
+    total: u32,
+    senders: BTreeMap<u32, MessageSender>,
+    waiting: HashSet<u32>,
+    deadline: Instant,
+}
+
+/// Runs the shards, restarts them when they die and reshards when Discord recommends it.
+///
+/// Keeps [`State::senders`] in sync with the currently running shards.
+pub struct ShardSupervisor<TRouter> {
+    state: Arc<State>,
+    router: TRouter,
+    shard_config: Config<IdentifyQueue>,
+    layout: ShardLayout,
+    config: SupervisorConfig,
+    runners: JoinSet<ShardExit>,
+    runner_infos: HashMap<tokio::task::Id, RunnerInfo>,
+    generation: u64,
+    active_generation: Arc<AtomicU64>,
+    total: u32,
+    pending: Option<PendingGeneration>,
+    sessions: SessionStore,
+    ready_sender: mpsc::UnboundedSender<(u64, ShardId)>,
+    ready_receiver: mpsc::UnboundedReceiver<(u64, ShardId)>,
+}
+
+impl<TRouter> ShardSupervisor<TRouter>
+where
+    TRouter: Service<
+            (ContextFactory, Interaction),
+            Response = (),
+            Error = (),
+            Future: Future<Output = Result<(), ()>> + Send,
+        > + Clone
+        + Send
+        + 'static,
+{
+    pub fn new(
+        state: Arc<State>,
+        router: TRouter,
+        shard_config: Config<IdentifyQueue>,
+        layout: ShardLayout,
+        config: SupervisorConfig,
+    ) -> Self {
+        let (ready_sender, ready_receiver) = mpsc::unbounded_channel();
+        ShardSupervisor {
+            state,
+            router,
+            shard_config,
+            layout,
+            config,
+            runners: JoinSet::new(),
+            runner_infos: HashMap::new(),
+            generation: 0,
+            active_generation: Arc::new(AtomicU64::new(0)),
+            total: 0,
+            pending: None,
+            sessions: SessionStore::default(),
+            ready_sender,
+            ready_receiver,
+        }
+    }
+
+    /// Runs the given shards until all of them exited after a shutdown.
+    ///
+    /// Returns the sessions of the shards that can be resumed.
+    #[instrument(level = "info", skip_all)]
+    pub async fn run(
+        mut self,
+        shards: impl IntoIterator<Item = Shard<IdentifyQueue>>,
+    ) -> SessionStore {
+        for shard in shards {
+            self.total = shard.id().total();
+            self.spawn_runner(shard, self.generation, 0, None);
+        }
+
+        let start = Instant::now() + self.config.reshard_interval;
+        let mut reshard_interval = tokio::time::interval_at(start, self.config.reshard_interval);
+        reshard_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            let pending_deadline = self.pending.as_ref().map(|pending| pending.deadline);
+            tokio::select! {
+                exit = self.runners.join_next_with_id() => match exit {
+                    Some(Ok((task_id, exit))) => self.handle_exit(task_id, Ok(exit)),
+                    Some(Err(error)) => self.handle_exit(error.id(), Err(error)),
+                    None => break,
+                },
+                Some((generation, shard_id)) = self.ready_receiver.recv() => {
+                    self.handle_ready(generation, shard_id);
+                }
+                _ = reshard_interval.tick() => self.check_reshard().await,
+                () = tokio::time::sleep_until(pending_deadline.unwrap_or_else(Instant::now)),
+                    if pending_deadline.is_some() =>
+                {
+                    self.abandon_pending();
+                }
+            }
+        }
+
+        info!(resumable = self.sessions.len(), "All shards exited");
+        self.sessions
+    }
+
+    fn spawn_runner(
+        &mut self,
+        shard: Shard<IdentifyQueue>,
+        generation: u64,
+        failures: u32,
+        delay: Option<Duration>,
+    ) {
+        let shard_id = shard.id();
+        self.register_sender(generation, shard_id, shard.sender());
+
+        let handle = RunnerHandle {
+            generation,
+            active_generation: self.active_generation.clone(),
+            ready_sender: self.ready_sender.clone(),
+        };
+        let router = self.router.clone();
+        let context_factory = ContextFactory::new(self.state.clone());
+        let state = self.state.clone();
+        let abort_handle = self.runners.spawn(async move {
+            if let Some(delay) = delay {
+                tokio::time::sleep(delay).await;
+                if state.shutdown.load(Ordering::Acquire) {
+                    return ShardExit::Shutdown(None);
+                }
+                if handle.is_retired() {
+                    return ShardExit::Retired;
+                }
+            }
+
+            shard_runner(router, context_factory, shard, handle).await
+        });
+
+        self.runner_infos.insert(
+            abort_handle.id(),
+            RunnerInfo {
+                shard_id,
+                generation,
+                started: Instant::now() + delay.unwrap_or_default(),
+                failures,
+                abort_handle,
+            },
+        );
+    }
+
+    fn register_sender(&mut self, generation: u64, shard_id: ShardId, sender: MessageSender) {
+        match &mut self.pending {
+            Some(pending) if pending.generation == generation => {
+                pending.senders.insert(shard_id.number(), sender);
+            }
+            _ => {
+                self.state
+                    .senders
+                    .write()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .insert(shard_id.number(), sender);
+            }
+        }
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    fn handle_exit(&mut self, task_id: tokio::task::Id, exit: Result<ShardExit, JoinError>) {
+        let Some(info) = self.runner_infos.remove(&task_id) else {
+            error!("Unknown shard runner exited");
+            return;
+        };
+
+        match exit {
+            Ok(ShardExit::Shutdown(session)) => {
+                debug!(shard.id = %info.shard_id, "Shard runner exited after shutdown");
+                if let Some(session) = session {
+                    self.sessions.insert(info.shard_id, session);
+                }
+                self.abort_unstarted();
+                return;
+            }
+            Ok(ShardExit::Retired) => {
+                debug!(shard.id = %info.shard_id, "Retired shard runner exited");
+                return;
+            }
+            Err(error) if error.is_cancelled() => {
+                debug!(shard.id = %info.shard_id, "Shard runner was cancelled");
+                return;
+            }
+            Ok(ShardExit::StreamEnded) => {
+                warn!(shard.id = %info.shard_id, "Shard stream ended unexpectedly");
+            }
+            Err(error) => error!(shard.id = %info.shard_id, %error, "Shard runner panicked"),
+        }
+
+        if self.state.shutdown.load(Ordering::Acquire) || info.generation < self.generation {
+            return;
+        }
+
+        let failures = if info.started.elapsed() > self.config.restart_backoff_max {
+            0
+        } else {
+            info.failures.saturating_add(1)
+        };
+        let delay = self.config.restart_backoff(failures);
+        info!(shard.id = %info.shard_id, ?delay, failures, "Restarting shard");
+
+        let shard = Shard::with_config(info.shard_id, self.shard_config.clone());
+        self.spawn_runner(shard, info.generation, failures, Some(delay));
+    }
+
+    /// Aborts runners that are not going to receive the shutdown close frame.
+    fn abort_unstarted(&mut self) {
+        let now = Instant::now();
+        let pending_generation = self.pending.take().map(|pending| pending.generation);
+        for info in self.runner_infos.values() {
+            if info.started > now || Some(info.generation) == pending_generation {
+                info.abort_handle.abort();
+            }
+        }
+    }
+
+    fn handle_ready(&mut self, generation: u64, shard_id: ShardId) {
+        let Some(pending) = &mut self.pending else {
+            return;
+        };
+        if pending.generation != generation {
+            return;
+        }
+
+        pending.waiting.remove(&shard_id.number());
+        debug!(
+            shard.id = %shard_id,
+            waiting = pending.waiting.len(),
+            "Shard of new generation ready",
+        );
+        if pending.waiting.is_empty() {
+            self.activate_pending();
+        }
+    }
+
+    #[instrument(level = "info", skip(self))]
+    fn activate_pending(&mut self) {
+        let Some(pending) = self.pending.take() else {
+            return;
+        };
+
+        self.generation = pending.generation;
+        self.total = pending.total;
+        self.active_generation
+            .store(pending.generation, Ordering::Release);
+
+        let old_senders = std::mem::replace(
+            &mut *self
+                .state
+                .senders
+                .write()
+                .unwrap_or_else(PoisonError::into_inner),
+            pending.senders,
+        );
+        for sender in old_senders.values() {
+            if let Err(error) = sender.close(CloseFrame::NORMAL) {
+                debug!(%error, "Could not close retired shard");
+            }
+        }
+
+        info!(
+            generation = self.generation,
+            total = self.total,
+            "Resharding complete",
+        );
+    }
+
+    #[instrument(level = "info", skip(self))]
+    fn abandon_pending(&mut self) {
+        let Some(pending) = self.pending.take() else {
+            return;
+        };
+
+        warn!(
+            generation = pending.generation,
+            waiting = pending.waiting.len(),
+            "New generation of shards did not become ready in time, keeping old shards",
+        );
+        for info in self.runner_infos.values() {
+            if info.generation == pending.generation {
+                info.abort_handle.abort();
+            }
+        }
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn check_reshard(&mut self) {
+        if self.pending.is_some() || self.state.shutdown.load(Ordering::Acquire) {
+            return;
+        }
+        if self.layout.total.is_some() {
+            // The other processes running the same layout could not follow
+            return;
+        }
+
+        let info = match self.state.client.gateway().authed().await {
+            Ok(response) => match response.model().await {
+                Ok(info) => info,
+                Err(error) => {
+                    warn!(%error, "Could not deserialize gateway info");
+                    return;
+                }
+            },
+            Err(error) => {
+                warn!(%error, "Could not request gateway info");
+                return;
+            }
+        };
+
+        let total = self.layout.total(info.shards);
+        if total == self.total {
+            debug!(total, "Shard count is still up to date");
+            return;
+        }
+
+        let range = match self.layout.non_empty_shards(total) {
+            Ok(range) => range,
+            Err(error) => {
+                warn!(%error, "Not resharding");
+                return;
+            }
+        };
+        let shard_count = range.end - range.start;
+        if info.session_start_limit.remaining < shard_count {
+            warn!(
+                recommended = total,
+                remaining = info.session_start_limit.remaining,
+                "Not enough remaining session starts to reshard",
+            );
+            return;
+        }
+
+        info!(
+            current = self.total,
+            recommended = total,
+            ?range,
+            "Starting to reshard",
+        );
+
+        let concurrency = u32::from(info.session_start_limit.max_concurrency.max(1));
+        let identify_time = IDENTIFY_INTERVAL.saturating_mul(shard_count.div_ceil(concurrency));
+        let generation = self.generation + 1;
+        self.pending = Some(PendingGeneration {
+            generation,
+            total,
+            senders: BTreeMap::new(),
+            waiting: range.clone().collect(),
+            deadline: Instant::now() + identify_time + RESHARD_GRACE_PERIOD,
+        });
+
+        let shards = create_iterator(range, total, self.shard_config.clone(), |_, builder| {
+            builder.build()
+        });
+        for shard in shards {
+            self.spawn_runner(shard, generation, 0, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cluster(index: u32, count: u32) -> ShardLayout {
+        ShardLayout {
+            total: None,
+            selection: ShardSelection::Cluster { index, count },
+        }
+    }
+
+    #[test]
+    fn uneven_clusters() {
+        let shards: Vec<_> = (0..4).map(|index| cluster(index, 4).shards(10)).collect();
+        assert_eq!(shards, [0..3, 3..6, 6..9, 9..10]);
+    }
+
+    #[test]
+    fn trailing_empty_cluster() {
+        assert_eq!(cluster(1, 3).non_empty_shards(4), Ok(2..4));
+        assert!(cluster(2, 3).shards(4).is_empty());
+        assert_eq!(
+            cluster(2, 3).non_empty_shards(4),
+            Err(ShardLayoutError::NoShards { total: 4 })
+        );
+    }
+
+    #[test]
+    fn range_clamped_to_total() {
+        let layout = ShardLayout {
+            total: Some(8),
+            selection: ShardSelection::Range { start: 6, end: 10 },
+        };
+        assert_eq!(layout.total(16), 8);
+        assert_eq!(layout.non_empty_shards(8), Ok(6..8));
+        assert_eq!(
+            layout.non_empty_shards(4),
+            Err(ShardLayoutError::NoShards { total: 4 })
+        );
+    }
+
+    #[test]
+    fn all_shards() {
+        let layout = ShardLayout {
+            total: None,
+            selection: ShardSelection::All,
+        };
+        assert_eq!(layout.total(16), 16);
+        assert_eq!(layout.non_empty_shards(16), Ok(0..16));
+    }
+}
@@ -0,0 +1,89 @@
+use crate::commands::Commands;
+use crate::context::{AdminGuildId, State};
+use std::mem;
+use tracing::{error, info, instrument, warn};
+
+/// Exit code used when a second signal interrupts the graceful shutdown.
+const FORCED_EXIT_CODE: i32 = 130;
+
+/// Shuts down on the first SIGINT or SIGTERM and exits immediately on the second.
+///
+/// Only signals count, a shutdown started by a command is still drained on the first signal.
+#[instrument(level = "debug", skip(state, stopping))]
+fn stop(state: &State, stopping: &mut bool, signal: &str) {
+    if mem::replace(stopping, true) {
+        warn!(
+            signal,
+            "Received signal during shutdown, exiting immediately"
+        );
+        std::process::exit(FORCED_EXIT_CODE);
+    }
+
+    info!(signal, "Received signal, shutting down");
+    if let Err(error) = state.send_shutdown() {
+        error!(?error, "Sending shutdown from signal handler failed");
+    }
+}
+
+/// Reloads the configuration and registers the commands again.
+#[instrument(level = "info", skip(state))]
+async fn reload(state: &State) {
+    if let Err(error) = state.reload_config() {
+        warn!(%error, "Could not reload config");
+    }
+
+    let Some(&AdminGuildId(admin_guild_id)) = state.extensions.get::<AdminGuildId>() else {
+        warn!("Admin guild id is missing from the state, not reloading commands");
+        return;
+    };
+    match Commands::update_commands(&state.interaction_client(), admin_guild_id).await {
+        Ok(diff) => info!(%diff, "Reloaded commands"),
+        Err(error) => warn!(%error, "Could not reload commands"),
+    }
+}
+
+/// Handles SIGINT and SIGTERM by shutting down and SIGHUP by reloading, until the process exits.
+#[cfg(unix)]
+#[instrument(skip(state))]
+pub async fn handle_signals(state: &State) {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let signals = (
+        signal(SignalKind::interrupt()),
+        signal(SignalKind::terminate()),
+        signal(SignalKind::hangup()),
+    );
+    let (Ok(mut interrupt), Ok(mut terminate), Ok(mut hangup)) = signals else {
+        error!("Could not install signal handlers, sending shutdown");
+        if let Err(error) = state.send_shutdown() {
+            error!(?error, "Sending shutdown from signal handler failed");
+        }
+        return;
+    };
+
+    let mut stopping = false;
+    loop {
+        tokio::select! {
+            _ = interrupt.recv() => stop(state, &mut stopping, "SIGINT"),
+            _ = terminate.recv() => stop(state, &mut stopping, "SIGTERM"),
+            _ = hangup.recv() => reload(state).await,
+        }
+    }
+}
+
+/// Handles Ctrl-C by shutting down, the other signals do not exist on this platform.
+#[cfg(not(unix))]
+#[instrument(skip(state))]
+pub async fn handle_signals(state: &State) {
+    let mut stopping = false;
+    loop {
+        if let Err(error) = tokio::signal::ctrl_c().await {
+            error!(%error, "Could not install ctrl-c handler, sending shutdown");
+            if let Err(error) = state.send_shutdown() {
+                error!(?error, "Sending shutdown from signal handler failed");
+            }
+            return;
+        }
+        stop(state, &mut stopping, "ctrl-c");
+    }
+}
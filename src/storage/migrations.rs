@@ -0,0 +1,72 @@
+use crate::storage::StorageError;
+use rusqlite::Connection;
+use tracing::{info, instrument};
+
+/// Schema changes, in the order they are applied. Applied migrations must never change.
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE guild_records (
+        owner_id INTEGER NOT NULL,
+        key TEXT NOT NULL,
+        value TEXT NOT NULL,
+        updated_at INTEGER NOT NULL DEFAULT (unixepoch()),
+        PRIMARY KEY (owner_id, key)
+    ) WITHOUT ROWID;
+    CREATE TABLE user_records (
+        owner_id INTEGER NOT NULL,
+        key TEXT NOT NULL,
+        value TEXT NOT NULL,
+        updated_at INTEGER NOT NULL DEFAULT (unixepoch()),
+        PRIMARY KEY (owner_id, key)
+    ) WITHOUT ROWID;
+",
+    "
+    CREATE TABLE blocklist (
+        kind TEXT NOT NULL,
+        target_id INTEGER NOT NULL,
+        reason TEXT,
+        blocked_at INTEGER NOT NULL DEFAULT (unixepoch()),
+        PRIMARY KEY (kind, target_id)
+    ) WITHOUT ROWID;
+",
+    "
+    CREATE TABLE scheduled_jobs (
+        id INTEGER PRIMARY KEY,
+        kind TEXT NOT NULL,
+        payload TEXT NOT NULL,
+        due_at INTEGER NOT NULL,
+        created_at INTEGER NOT NULL DEFAULT (unixepoch())
+    );
+    CREATE INDEX scheduled_jobs_due_at ON scheduled_jobs (due_at);
+",
+    "
+    CREATE TABLE reminders (
+        job_id INTEGER PRIMARY KEY REFERENCES scheduled_jobs (id) ON DELETE CASCADE,
+        user_id INTEGER NOT NULL,
+        message TEXT NOT NULL,
+        due_at INTEGER NOT NULL
+    );
+    CREATE INDEX reminders_user_id ON reminders (user_id, due_at);
+",
+    "
+    ALTER TABLE scheduled_jobs ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0;
+",
+];
+
+/// Applies the migrations newer than the database's `user_version`, returning the resulting version.
+#[instrument(level = "debug", skip(connection))]
+pub fn run(connection: &mut Connection) -> Result<usize, StorageError> {
+    let applied: usize = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+        let version = index + 1;
+        let migrate = |connection: &mut Connection| {
+            let transaction = connection.transaction()?;
+            transaction.execute_batch(migration)?;
+            transaction.pragma_update(None, "user_version", version)?;
+            transaction.commit()
+        };
+        migrate(connection).map_err(|source| StorageError::Migration { version, source })?;
+        info!(version, "Applied migration");
+    }
+    Ok(MIGRATIONS.len().max(applied))
+}
@@ -0,0 +1,74 @@
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::Connection;
+use std::path::Path;
+use thiserror::Error;
+use tracing::{info, instrument};
+use twilight_model::id::marker::{GuildMarker, UserMarker};
+
+mod migrations;
+mod records;
+
+pub use records::{RecordOwner, Records};
+
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("Database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("Connection pool error: {0}")]
+    Pool(#[from] r2d2::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Storage task failed: {0}")]
+    Join(#[from] tokio::task::JoinError),
+    #[error("Migration {version} failed: {source}")]
+    Migration {
+        version: usize,
+        source: rusqlite::Error,
+    },
+}
+
+/// Local database the bot keeps data in across restarts.
+#[derive(Clone, Debug)]
+pub struct Storage {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+impl Storage {
+    /// Opens or creates the database file and migrates it to the current schema.
+    #[instrument(level = "info")]
+    pub fn open(path: &Path, pool_size: u32) -> Result<Self, StorageError> {
+        let manager = SqliteConnectionManager::file(path).with_init(|connection| {
+            // Readers don't block the writer and writers wait for each other instead of failing
+            connection.execute_batch(
+                "PRAGMA journal_mode = WAL;
+                PRAGMA busy_timeout = 5000;
+                PRAGMA foreign_keys = ON;",
+            )
+        });
+        let pool = Pool::builder().max_size(pool_size).build(manager)?;
+        let version = migrations::run(&mut *pool.get()?)?;
+        info!(version, "Opened storage");
+        Ok(Storage { pool })
+    }
+
+    /// Runs the closure with a pooled connection, on a thread where blocking is fine.
+    pub async fn with_connection<T, F>(&self, f: F) -> Result<T, StorageError>
+    where
+        F: FnOnce(&mut Connection) -> Result<T, StorageError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let pool = self.pool.clone();
+        tokio::task::spawn_blocking(move || f(&mut *pool.get()?)).await?
+    }
+
+    /// Records stored per guild.
+    pub fn guilds(&self) -> Records<GuildMarker> {
+        Records::new(self.clone())
+    }
+
+    /// Records stored per user.
+    pub fn users(&self) -> Records<UserMarker> {
+        Records::new(self.clone())
+    }
+}
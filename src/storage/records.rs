@@ -0,0 +1,142 @@
+use crate::storage::{Storage, StorageError};
+use derive_where::derive_where;
+use rusqlite::{OptionalExtension, TransactionBehavior, params};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{GuildMarker, UserMarker};
+
+/// Owners of records, each stored in their own table.
+pub trait RecordOwner {
+    const TABLE: &'static str;
+}
+
+impl RecordOwner for GuildMarker {
+    const TABLE: &'static str = "guild_records";
+}
+
+impl RecordOwner for UserMarker {
+    const TABLE: &'static str = "user_records";
+}
+
+/// JSON values stored by key for each guild or user.
+#[derive_where(Clone, Debug)]
+pub struct Records<TOwner> {
+    storage: Storage,
+    owner: PhantomData<fn() -> TOwner>,
+}
+
+impl<TOwner: RecordOwner> Records<TOwner> {
+    pub(super) fn new(storage: Storage) -> Self {
+        Records {
+            storage,
+            owner: PhantomData,
+        }
+    }
+
+    /// The value stored under the key, or `None` if there is none.
+    pub async fn get<T>(&self, owner_id: Id<TOwner>, key: &str) -> Result<Option<T>, StorageError>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        // The owner type is not 'static, so only plain values go to the blocking thread
+        let query = format!(
+            "SELECT value FROM {} WHERE owner_id = ?1 AND key = ?2",
+            TOwner::TABLE
+        );
+        let owner_id = owner_id.get().cast_signed();
+        let key = key.to_owned();
+        self.storage
+            .with_connection(move |connection| {
+                let value: Option<String> = connection
+                    .query_row(&query, params![owner_id, key], |row| row.get(0))
+                    .optional()?;
+                Ok(value
+                    .map(|value| serde_json::from_str(&value))
+                    .transpose()?)
+            })
+            .await
+    }
+
+    /// Stores the number under the key unless a higher one is stored, returning the highest.
+    pub async fn set_max(
+        &self,
+        owner_id: Id<TOwner>,
+        key: &str,
+        value: u64,
+    ) -> Result<u64, StorageError> {
+        // Values are stored as JSON text, so they have to be compared as numbers
+        let query = format!(
+            "INSERT INTO {} (owner_id, key, value) VALUES (?1, ?2, ?3)
+            ON CONFLICT (owner_id, key)
+            DO UPDATE SET
+                value = max(CAST(value AS INTEGER), CAST(excluded.value AS INTEGER)),
+                updated_at = unixepoch()
+            RETURNING value",
+            TOwner::TABLE
+        );
+        let owner_id = owner_id.get().cast_signed();
+        let key = key.to_owned();
+        let value = serde_json::to_string(&value)?;
+        self.storage
+            .with_connection(move |connection| {
+                let highest: String =
+                    connection
+                        .query_row(&query, params![owner_id, key, value], |row| row.get(0))?;
+                Ok(serde_json::from_str(&highest)?)
+            })
+            .await
+    }
+
+    /// Changes the value stored under the key, starting from the default if there is none.
+    ///
+    /// The value is read and written in one transaction, so concurrent updates are not lost.
+    /// It is only written if `update` succeeds, whose result is returned.
+    pub async fn update<T, R, E, F>(
+        &self,
+        owner_id: Id<TOwner>,
+        key: &str,
+        update: F,
+    ) -> Result<Result<R, E>, StorageError>
+    where
+        T: Serialize + DeserializeOwned + Default,
+        F: FnOnce(&mut T) -> Result<R, E> + Send + 'static,
+        R: Send + 'static,
+        E: Send + 'static,
+    {
+        let select = format!(
+            "SELECT value FROM {} WHERE owner_id = ?1 AND key = ?2",
+            TOwner::TABLE
+        );
+        let upsert = format!(
+            "INSERT INTO {} (owner_id, key, value) VALUES (?1, ?2, ?3)
+            ON CONFLICT (owner_id, key)
+            DO UPDATE SET value = excluded.value, updated_at = unixepoch()",
+            TOwner::TABLE
+        );
+        let owner_id = owner_id.get().cast_signed();
+        let key = key.to_owned();
+        self.storage
+            .with_connection(move |connection| {
+                // Taking the write lock up front, so a concurrent update waits instead of failing
+                let transaction =
+                    connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+                let stored: Option<String> = transaction
+                    .query_row(&select, params![owner_id, key], |row| row.get(0))
+                    .optional()?;
+                let mut value: T = stored
+                    .map(|value| serde_json::from_str(&value))
+                    .transpose()?
+                    .unwrap_or_default();
+                let result = update(&mut value);
+                if result.is_ok() {
+                    let value = serde_json::to_string(&value)?;
+                    transaction.execute(&upsert, params![owner_id, key, value])?;
+                    transaction.commit()?;
+                }
+                Ok(result)
+            })
+            .await
+    }
+}
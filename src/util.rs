@@ -1,4 +1,4 @@
-use std::fmt::{Debug, Formatter};
+use std::fmt::{Debug, Formatter, Write};
 
 pub struct OmitDebug;
 
@@ -7,3 +7,79 @@ impl Debug for OmitDebug {
         write!(f, "[omitted]")
     }
 }
+
+/// Longest message content Discord accepts, in characters.
+pub const MAX_CONTENT_LENGTH: usize = 2000;
+/// Room kept for the note about lines left out by [`join_lines`].
+const OMITTED_NOTE_LENGTH: usize = 32;
+
+/// Shortens the text to at most `max` characters, ending it with an ellipsis if it was cut.
+pub fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_owned();
+    }
+    let mut truncated: String = text.chars().take(max.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Joins the lines into message content, leaving out the lines that don't fit and noting how many.
+pub fn join_lines(lines: &[String]) -> String {
+    let mut content = String::new();
+    let mut length = 0;
+    for (index, line) in lines.iter().enumerate() {
+        let line_length = line.chars().count() + 1;
+        let limit = if index + 1 == lines.len() {
+            MAX_CONTENT_LENGTH
+        } else {
+            MAX_CONTENT_LENGTH - OMITTED_NOTE_LENGTH
+        };
+        if length + line_length > limit {
+            _ = write!(content, "…and {} more.", lines.len() - index);
+            break;
+        }
+        content.push_str(line);
+        content.push('\n');
+        length += line_length;
+    }
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_counts_characters() {
+        assert_eq!(truncate("short", 5), "short");
+        assert_eq!(truncate("too long", 5), "too …");
+        assert_eq!(truncate("ääääää", 4), "äää…");
+        assert_eq!(truncate("text", 0), "…");
+    }
+
+    #[test]
+    fn join_lines_that_fit() {
+        let lines = ["a".to_owned(), "b".to_owned()];
+        assert_eq!(join_lines(&lines), "a\nb\n");
+        assert_eq!(join_lines(&[]), "");
+    }
+
+    #[test]
+    fn join_lines_notes_omitted_lines() {
+        let lines = vec!["x".repeat(99); 30];
+        let content = join_lines(&lines);
+        assert!(content.chars().count() <= MAX_CONTENT_LENGTH);
+        assert_eq!(content.lines().filter(|line| line.len() == 99).count(), 19);
+        assert!(content.ends_with("…and 11 more."));
+    }
+
+    #[test]
+    fn join_lines_keeps_a_last_line_that_fits() {
+        // The last line may use the room kept for the note
+        let mut lines = vec!["x".repeat(99); 19];
+        lines.push("x".repeat(MAX_CONTENT_LENGTH - 19 * 100 - 1));
+        let content = join_lines(&lines);
+        assert_eq!(content.chars().count(), MAX_CONTENT_LENGTH);
+        assert!(!content.contains("more."));
+    }
+}
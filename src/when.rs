@@ -0,0 +1,205 @@
+use chrono::{DateTime, NaiveTime, TimeDelta, Utc};
+use thiserror::Error;
+
+/// Furthest into the future a parsed time can be.
+pub const MAX_AHEAD: TimeDelta = TimeDelta::days(365);
+
+/// Reason a time could not be parsed, shown to the user.
+#[derive(Clone, Eq, PartialEq, Debug, Error)]
+pub enum WhenError {
+    #[error("`{0}` is not a time, use a duration like `1h30m` or a time like `tomorrow 9am`.")]
+    Invalid(String),
+    #[error("That time has already passed.")]
+    Passed,
+    #[error("That is too far ahead, at most a year is allowed.")]
+    TooFarAhead,
+}
+
+/// Parses a duration like `1h30m` or `in 2 days`, or a time like `17:30`, `today 5pm` or `tomorrow 9am`.
+///
+/// Times of day are in UTC, a time without a day is its next occurrence.
+pub fn parse(input: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, WhenError> {
+    let lowercase = input.trim().to_lowercase();
+    let normalized = lowercase.strip_prefix("in ").unwrap_or(&lowercase).trim();
+    let time = parse_duration(normalized)
+        .and_then(|duration| now.checked_add_signed(duration))
+        .or_else(|| parse_day_time(normalized, now))
+        .ok_or_else(|| WhenError::Invalid(input.trim().to_owned()))?;
+
+    if time <= now {
+        Err(WhenError::Passed)
+    } else if time - now > MAX_AHEAD {
+        Err(WhenError::TooFarAhead)
+    } else {
+        Ok(time)
+    }
+}
+
+/// Parses amounts with units, e.g. `1h30m`, `2 days` or `1 hour, 15 minutes`.
+fn parse_duration(input: &str) -> Option<TimeDelta> {
+    if input.is_empty() {
+        return None;
+    }
+
+    let mut total = TimeDelta::zero();
+    let mut rest = input;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let amount: i64 = rest[..digits].parse().ok()?;
+        rest = rest[digits..].trim_start();
+        let letters = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        let part = match &rest[..letters] {
+            "s" | "sec" | "secs" | "second" | "seconds" => TimeDelta::try_seconds(amount),
+            "m" | "min" | "mins" | "minute" | "minutes" => TimeDelta::try_minutes(amount),
+            "h" | "hr" | "hrs" | "hour" | "hours" => TimeDelta::try_hours(amount),
+            "d" | "day" | "days" => TimeDelta::try_days(amount),
+            "w" | "week" | "weeks" => TimeDelta::try_weeks(amount),
+            _ => None,
+        }?;
+        total = total.checked_add(&part)?;
+        rest = rest[letters..].trim_start_matches([' ', ',']);
+    }
+    Some(total)
+}
+
+/// Parses an optional `today` or `tomorrow` followed by a time of day.
+fn parse_day_time(input: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let today = now.date_naive();
+    let (day, time) = if let Some(time) = input.strip_prefix("tomorrow") {
+        (Some(today.succ_opt()?), time)
+    } else if let Some(time) = input.strip_prefix("today") {
+        (Some(today), time)
+    } else {
+        (None, input)
+    };
+    let time = time.trim();
+    let time = time.strip_prefix("at ").unwrap_or(time).trim();
+
+    let Some(day) = day else {
+        let time = parse_time_of_day(time)?;
+        let today_at = today.and_time(time).and_utc();
+        return if today_at > now {
+            Some(today_at)
+        } else {
+            Some(today.succ_opt()?.and_time(time).and_utc())
+        };
+    };
+    if time.is_empty() {
+        // `tomorrow` on its own is the same time tomorrow
+        return Some(day.and_time(now.time()).and_utc());
+    }
+    Some(day.and_time(parse_time_of_day(time)?).and_utc())
+}
+
+/// Parses a 24-hour time like `17:30` or a 12-hour time like `5pm` or `5:30 pm`.
+fn parse_time_of_day(input: &str) -> Option<NaiveTime> {
+    let (clock, is_pm) = if let Some(clock) = input.strip_suffix("am") {
+        (clock.trim_end(), Some(false))
+    } else if let Some(clock) = input.strip_suffix("pm") {
+        (clock.trim_end(), Some(true))
+    } else {
+        (input, None)
+    };
+    if !clock.chars().all(|c| c.is_ascii_digit() || c == ':') {
+        return None;
+    }
+
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) if minute.len() == 2 => (hour.parse().ok()?, minute.parse().ok()?),
+        Some(_) => return None,
+        None => (clock.parse::<u32>().ok()?, 0),
+    };
+    let hour = match is_pm {
+        Some(_) if !(1..=12).contains(&hour) => return None,
+        Some(is_pm) => hour % 12 + u32::from(is_pm) * 12,
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        "2026-10-18T12:00:00Z".parse().unwrap()
+    }
+
+    fn at(time: &str) -> DateTime<Utc> {
+        time.parse().unwrap()
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse("1h30m", now()), Ok(at("2026-10-18T13:30:00Z")));
+        assert_eq!(parse("in 2 days", now()), Ok(at("2026-10-20T12:00:00Z")));
+        assert_eq!(
+            parse("1 hour, 15 minutes", now()),
+            Ok(at("2026-10-18T13:15:00Z"))
+        );
+    }
+
+    #[test]
+    fn day_and_time() {
+        assert_eq!(parse("tomorrow 9am", now()), Ok(at("2026-10-19T09:00:00Z")));
+        assert_eq!(
+            parse("Tomorrow at 5:30 PM", now()),
+            Ok(at("2026-10-19T17:30:00Z"))
+        );
+        assert_eq!(parse("tomorrow", now()), Ok(at("2026-10-19T12:00:00Z")));
+        assert_eq!(parse("17:30", now()), Ok(at("2026-10-18T17:30:00Z")));
+        // Times that passed today are tomorrow
+        assert_eq!(parse("9:30", now()), Ok(at("2026-10-19T09:30:00Z")));
+    }
+
+    #[test]
+    fn twelve_hour_clock() {
+        assert_eq!(parse("12am", now()), Ok(at("2026-10-19T00:00:00Z")));
+        assert_eq!(
+            parse("tomorrow 12pm", now()),
+            Ok(at("2026-10-19T12:00:00Z"))
+        );
+        assert_eq!(parse("12:30pm", now()), Ok(at("2026-10-18T12:30:00Z")));
+        assert_eq!(
+            parse("0am", now()),
+            Err(WhenError::Invalid("0am".to_owned()))
+        );
+        assert_eq!(
+            parse("13pm", now()),
+            Err(WhenError::Invalid("13pm".to_owned()))
+        );
+    }
+
+    #[test]
+    fn passed() {
+        assert_eq!(parse("today 9am", now()), Err(WhenError::Passed));
+        assert_eq!(parse("0m", now()), Err(WhenError::Passed));
+    }
+
+    #[test]
+    fn too_far_ahead() {
+        assert_eq!(parse("365d", now()), Ok(at("2027-10-18T12:00:00Z")));
+        assert_eq!(parse("366d", now()), Err(WhenError::TooFarAhead));
+        assert_eq!(
+            parse("9999999999999w", now()),
+            Err(WhenError::Invalid("9999999999999w".to_owned()))
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(parse("", now()), Err(WhenError::Invalid(String::new())));
+        assert_eq!(
+            parse("soon", now()),
+            Err(WhenError::Invalid("soon".to_owned()))
+        );
+        assert_eq!(
+            parse("5 parsecs", now()),
+            Err(WhenError::Invalid("5 parsecs".to_owned()))
+        );
+    }
+}